@@ -2,114 +2,199 @@
 //!
 //! ## Example usage:
 //!
-//! ```rust
-//! use anythingllm::client::AnythingLLMClient;
+//! ```rust,no_run
+//! use ayda::anythingllm::client::AnythingLLMClient;
 //!
 //! #[tokio::main]
 //! async fn main() {
-//!     let client = AnythingLLMClient::new("127.0.0.1", "8080", "api_key");
+//!     let client = AnythingLLMClient::new("127.0.0.1", "8080", "api_key").unwrap();
 //!     let response = client.get_auth().await.unwrap();
 //!     assert_eq!(client.base_url_api_v1, "http://127.0.0.1:8080/api/v1");
 //! }
 //! ```
 //!
+//! A client for an instance behind a reverse proxy can be created from a full base URL:
+//!
+//! ```rust,no_run
+//! use ayda::anythingllm::client::{AnythingLLMClient, ClientOptions};
+//!
+//! let client = AnythingLLMClient::with_options(
+//!     "https://example.com/anythingllm",
+//!     "api_key",
+//!     &ClientOptions::default(),
+//! )
+//! .unwrap();
+//! assert_eq!(client.base_url_api_v1, "https://example.com/anythingllm/api/v1");
+//! ```
+//!
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::multipart::Form;
-use reqwest::Response;
+use reqwest::{Method, RequestBuilder, Response, StatusCode, Url};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use tokio::sync::OwnedSemaphorePermit;
+use tracing::Instrument;
 
 use crate::anythingllm::error::LLMError;
+use crate::anythingllm::limiter::{EndpointClass, Limiter, Limits};
+use crate::anythingllm::retry::RetryPolicy;
 
 #[derive(Serialize, Deserialize)]
 struct AuthResponse {
     authenticated: bool,
 }
 
+/// Connection options for an AnythingLLM client.
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    /// PEM files of additional CA certificates to trust.
+    pub ca_certificates: Vec<PathBuf>,
+    /// The timeout for each request. Streamed chat responses are only subject to the connect
+    /// timeout, as a long answer can legitimately take longer than this.
+    pub timeout: Option<Duration>,
+    /// The URL of an HTTP(S) proxy to send requests through.
+    pub proxy: Option<String>,
+    /// Limits on concurrent requests and request rate.
+    pub limits: Limits,
+    /// The policy used to retry failed requests.
+    pub retry_policy: RetryPolicy,
+}
+
 /// An AnythingLLM client.
 #[derive(Debug, Clone)]
 pub struct AnythingLLMClient {
     pub base_url: String,
     pub base_url_api_v1: String,
     pub client: reqwest::Client,
+    pub retry_policy: RetryPolicy,
+    pub timeout: Option<Duration>,
+    pub limiter: Arc<Limiter>,
 }
 
 impl AnythingLLMClient {
-    pub fn new(ip: &str, port: &str, api_key: &str) -> Self {
+    /// Create a client for the instance at `ip` and `port`, with the default options.
+    pub fn new(ip: &str, port: &str, api_key: &str) -> Result<Self, LLMError> {
+        let base_url = format!("http://{}:{}", ip, port);
+        Self::with_options(&base_url, api_key, &ClientOptions::default())
+    }
+
+    /// Create a client for the instance at `base_url`, e.g. `https://example.com/anythingllm`.
+    ///
+    /// The base URL may include a path prefix; the API is expected at `{base_url}/api/v1`.
+    pub fn with_options(
+        base_url: &str,
+        api_key: &str,
+        options: &ClientOptions,
+    ) -> Result<Self, LLMError> {
+        let base_url = base_url.trim().trim_end_matches('/');
+        let url = Url::parse(base_url).map_err(|e| {
+            LLMError::ConfigError(format!("invalid base URL '{}': {}", base_url, e))
+        })?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(LLMError::ConfigError(format!(
+                "unsupported scheme in base URL '{}'",
+                base_url
+            )));
+        }
+
         let mut headers = HeaderMap::new();
         headers.insert(
             "Authorization",
-            HeaderValue::from_str(&format!("Bearer {}", api_key)).unwrap(),
+            HeaderValue::from_str(&format!("Bearer {}", api_key))
+                .map_err(|_| LLMError::ConfigError("invalid API key".to_string()))?,
         );
-        let base_url = format!("http://{}:{}", ip, port);
-        let base_url_api_v1 = format!("http://{}:{}/api/v1", ip, port);
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()
-            .unwrap();
-
-        Self {
-            base_url,
-            base_url_api_v1,
-            client,
+
+        let mut builder = reqwest::Client::builder().default_headers(headers);
+
+        for path in &options.ca_certificates {
+            let pem = std::fs::read(path)?;
+            let certificate = reqwest::Certificate::from_pem(&pem).map_err(|e| {
+                LLMError::ConfigError(format!("invalid certificate {}: {}", path.display(), e))
+            })?;
+            builder = builder.add_root_certificate(certificate);
+        }
+
+        if let Some(timeout) = options.timeout {
+            builder = builder.connect_timeout(timeout);
         }
+
+        if let Some(proxy) = &options.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| LLMError::ConfigError(format!("invalid proxy '{}': {}", proxy, e)))?;
+            builder = builder.proxy(proxy);
+        }
+
+        let client = builder.build()?;
+
+        Ok(Self {
+            base_url: base_url.to_string(),
+            base_url_api_v1: format!("{}/api/v1", base_url),
+            client,
+            retry_policy: options.retry_policy.clone(),
+            timeout: options.timeout,
+            limiter: Arc::new(Limiter::new(&options.limits)),
+        })
+    }
+
+    /// Replace the policy used to retry failed requests.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
     }
 
-    // FIXME improve error handling to relay the error message
     pub async fn get(&self, endpoint: &str) -> Result<Response, LLMError> {
         let url = format!("{}/{}", self.base_url_api_v1, endpoint);
         let response = self
-            .client
-            .get(url.clone())
-            .send()
-            .await?
-            .error_for_status()?;
+            .send_with_retry(endpoint, || async { Ok(self.request(Method::GET, &url)) })
+            .await?;
 
-        Ok(response)
+        Self::check_status(endpoint, response).await
     }
 
     pub async fn post(&self, endpoint: &str, body: &Value) -> Result<Response, LLMError> {
         let url = format!("{}/{}", self.base_url_api_v1, endpoint);
         let response = self
-            .client
-            .post(url.clone())
-            .header("Content-Type", "application/json")
-            .body(body.to_string())
-            .send()
-            .await?
-            .error_for_status()?;
+            .send_with_retry(endpoint, || async {
+                Ok(self
+                    .request(Method::POST, &url)
+                    .header("Content-Type", "application/json")
+                    .body(body.to_string()))
+            })
+            .await?;
 
-        Ok(response)
+        Self::check_status(endpoint, response).await
     }
 
     pub async fn delete(&self, endpoint: &str, body: &Value) -> Result<Response, LLMError> {
         let url = format!("{}/{}", self.base_url, endpoint);
 
         let response = self
-            .client
-            .delete(url.clone())
-            .header("Content-Type", "application/json")
-            .header("Content-Length", body.to_string().len())
-            .body(body.to_string())
-            .send()
-            .await?
-            .error_for_status()?;
+            .send_with_retry(endpoint, || async {
+                Ok(self
+                    .request(Method::DELETE, &url)
+                    .header("Content-Type", "application/json")
+                    .header("Content-Length", body.to_string().len())
+                    .body(body.to_string()))
+            })
+            .await?;
 
-        Ok(response)
+        Self::check_status(endpoint, response).await
     }
-
     /// Get the authentication status from the AnythingLLM instance.
     pub async fn get_auth(&self) -> std::result::Result<bool, LLMError> {
         let response = match self.get("auth").await {
             Ok(response) => response,
-            Err(_) => return Err(LLMError::AuthError),
+            Err(LLMError::Unauthorized { .. }) => return Err(LLMError::AuthError),
+            Err(e) => return Err(e),
         };
 
-        let result = response
-            .json::<AuthResponse>()
-            .await
-            .expect("FIXME failed to parse json");
+        let result = response.json::<AuthResponse>().await?;
 
         match result.authenticated {
             true => Ok(true),
@@ -117,17 +202,172 @@ impl AnythingLLMClient {
         }
     }
 
-    pub async fn post_multipart(&self, endpoint: &str, form: Form) -> Result<Response, LLMError> {
+    /// Post a multipart form.
+    ///
+    /// The form is consumed when it is sent, so `make_form` is called to build a fresh one for
+    /// every attempt.
+    pub async fn post_multipart<F, Fut>(
+        &self,
+        endpoint: &str,
+        make_form: F,
+    ) -> Result<Response, LLMError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<Form, LLMError>>,
+    {
         let url = format!("{}/{}", self.base_url_api_v1, endpoint);
 
         let response = self
-            .client
-            .post(url.clone())
-            .multipart(form)
-            .send()
-            .await?
-            .error_for_status()?;
+            .send_with_retry(endpoint, || async {
+                Ok(self
+                    .request(Method::POST, &url)
+                    .multipart(make_form().await?))
+            })
+            .await?;
+
+        Self::check_status(endpoint, response).await
+    }
+
+    /// Post to an endpoint that streams its response.
+    ///
+    /// Unlike `post`, the request timeout is not applied, since the response body is read for as
+    /// long as the server keeps generating it. For the same reason the limiter's permit is returned
+    /// with the response, and the request counts against the limits until the permit is dropped.
+    pub async fn post_stream(
+        &self,
+        endpoint: &str,
+        body: &Value,
+    ) -> Result<(Response, OwnedSemaphorePermit), LLMError> {
+        let url = format!("{}/{}", self.base_url_api_v1, endpoint);
+        let (response, permit) = self
+            .send_with_permit(endpoint, || async {
+                Ok(self
+                    .client
+                    .post(url.clone())
+                    .header("Content-Type", "application/json")
+                    .body(body.to_string()))
+            })
+            .await?;
+
+        Ok((Self::check_status(endpoint, response).await?, permit))
+    }
+
+    /// Start building a request to `url`, applying the client's request timeout.
+    pub(crate) fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        match self.timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
+    }
 
+    /// Return the response if it was successful, or an `LLMError` that carries the status code,
+    /// endpoint and the server's error message.
+    pub(crate) async fn check_status(
+        endpoint: &str,
+        response: Response,
+    ) -> Result<Response, LLMError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        Err(LLMError::from_response(status, endpoint, &body))
+    }
+
+    /// Read the body of a response as text.
+    ///
+    /// NOTE: For some bad requests the API returns a "200 OK" status with the text "Bad Request"
+    /// rather than a "400 Bad Request", so that body is treated as an error too.
+    pub(crate) async fn response_text(
+        endpoint: &str,
+        response: Response,
+    ) -> Result<String, LLMError> {
+        let text = Self::check_status(endpoint, response).await?.text().await?;
+
+        if text == "Bad Request" {
+            return Err(LLMError::from_response(
+                StatusCode::BAD_REQUEST,
+                endpoint,
+                &text,
+            ));
+        }
+
+        Ok(text)
+    }
+
+    // Send the request built by `make_request`, retrying transient failures according to the
+    // client's retry policy. The final response is returned whatever its status.
+    //
+    // Each attempt waits for the client's limiter, and holds its permit until the response
+    // headers arrive. The permit is released while waiting to retry.
+    pub(crate) async fn send_with_retry<F, Fut>(
+        &self,
+        endpoint: &str,
+        make_request: F,
+    ) -> Result<Response, LLMError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<RequestBuilder, LLMError>>,
+    {
+        let (response, _permit) = self.send_with_permit(endpoint, make_request).await?;
         Ok(response)
     }
+
+    // As `send_with_retry`, but return the final attempt's limiter permit with its response, for
+    // responses whose body is read long after the headers arrive
+    async fn send_with_permit<F, Fut>(
+        &self,
+        endpoint: &str,
+        make_request: F,
+    ) -> Result<(Response, OwnedSemaphorePermit), LLMError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<RequestBuilder, LLMError>>,
+    {
+        let policy = &self.retry_policy;
+        let class = EndpointClass::of(endpoint);
+        let mut attempt = 1;
+
+        loop {
+            let span = tracing::info_span!("request", endpoint, attempt);
+            let result = async {
+                let permit = self.limiter.acquire(class).await;
+                let response = make_request().await?.send().await?;
+                Ok::<_, LLMError>((response, permit))
+            }
+            .instrument(span.clone())
+            .await;
+
+            let retry_after = match &result {
+                _ if attempt >= policy.max_attempts => return result,
+                Ok((response, _)) if RetryPolicy::is_retryable_status(response.status()) => {
+                    RetryPolicy::retry_after(response)
+                }
+                Err(LLMError::ReqwestError(e)) if RetryPolicy::is_retryable_error(e) => None,
+                _ => return result,
+            };
+
+            let Some(delay) = policy.delay(attempt, retry_after) else {
+                span.in_scope(|| {
+                    tracing::warn!(?retry_after, "server asked to wait too long, giving up")
+                });
+                return result;
+            };
+            span.in_scope(|| match &result {
+                Ok((response, _)) => tracing::warn!(
+                    status = %response.status(),
+                    ?delay,
+                    "request failed, retrying"
+                ),
+                Err(e) => tracing::warn!(error = %e, ?delay, "request failed, retrying"),
+            });
+
+            // release the limiter's permit while waiting, so other requests of the class can run
+            drop(result);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
 }
@@ -3,13 +3,16 @@ use std::path::Path;
 use regex::Regex;
 use reqwest::multipart;
 use reqwest::multipart::Form;
-use serde_json::{json, Value};
+use serde_json::json;
 use tempfile::NamedTempFile;
 use tracing::{event, span, Level};
 
 use crate::anythingllm::client::AnythingLLMClient;
-use crate::anythingllm::documents::{Document, DocumentMultipartResponse, DocumentsResponse, Item};
+use crate::anythingllm::documents::{
+    Document, DocumentMultipartResponse, DocumentsResponse, Item, RawTextMetadata,
+};
 use crate::anythingllm::error::LLMError;
+use crate::anythingllm::workspace::WorkspaceDocument;
 
 // Documents API /////////////////////////////////////////////////////////////////////////////
 
@@ -19,7 +22,6 @@ impl AnythingLLMClient {
         let documents_response = self
             .get("documents")
             .await?
-            .error_for_status()?
             .json::<DocumentsResponse>()
             .await?;
 
@@ -43,8 +45,7 @@ impl AnythingLLMClient {
                 "Path does not exist: {}",
                 path.to_string_lossy()
             );
-            return Err(LLMError::FileSystemError(std::io::Error::new(
-                std::io::ErrorKind::Other,
+            return Err(LLMError::FileSystemError(std::io::Error::other(
                 "File not found",
             )));
         }
@@ -70,15 +71,13 @@ impl AnythingLLMClient {
         .await
         .unwrap()?;
 
-        event!(Level::INFO, "creating multipart form");
-        let form = Self::create_multipart_form(&temp_file_path, path).await?;
-
         event!(Level::INFO, "posting multipart form");
-        let response = self.post_multipart("document/upload", form).await?;
-        if !response.status().is_success() {
-            event!(Level::ERROR, "post error");
-            return Err(LLMError::ServiceError(path.to_string_lossy().to_string()));
-        }
+        let response = self
+            .post_multipart("document/upload", || {
+                Self::create_multipart_form(&temp_file_path, path)
+            })
+            .await
+            .inspect_err(|e| event!(Level::ERROR, "post error: {}", e))?;
         event!(Level::INFO, "multipart form posted");
 
         let document = (&response
@@ -91,6 +90,51 @@ impl AnythingLLMClient {
         Ok(document)
     }
 
+    /// POST /document/raw-text
+    ///
+    /// Upload plain text as a document. The returned document's location can be passed to
+    /// `update_embeddings` to embed it in a workspace.
+    #[tracing::instrument(skip(self, text))]
+    pub async fn post_document_raw_text(
+        &self,
+        text: &str,
+        metadata: &RawTextMetadata,
+    ) -> Result<Document, LLMError> {
+        if text.trim().is_empty() {
+            return Err(LLMError::CustomError(format!(
+                "No text to upload for '{}'",
+                metadata.title
+            )));
+        }
+
+        let data = json!({ "textContent": text, "metadata": metadata });
+
+        let response = self
+            .post("document/raw-text", &data)
+            .await?
+            .json::<DocumentMultipartResponse>()
+            .await?;
+
+        Self::document_from_response(response, &metadata.title)
+    }
+
+    /// POST /document/upload-link
+    ///
+    /// Have the server scrape a web page and store it as a document. The returned document's
+    /// location can be passed to `update_embeddings` to embed it in a workspace.
+    #[tracing::instrument(skip(self))]
+    pub async fn post_document_upload_link(&self, link: &str) -> Result<Document, LLMError> {
+        let data = json!({ "link": link });
+
+        let response = self
+            .post("document/upload-link", &data)
+            .await?
+            .json::<DocumentMultipartResponse>()
+            .await?;
+
+        Self::document_from_response(response, link)
+    }
+
     /// DELETE /api/system/remove-documents
     /// Delete documents from the repository
     /// NOTE: This is not documented in the API so possibly unstable
@@ -101,39 +145,48 @@ impl AnythingLLMClient {
         // create json with key "names" and value of document_ids
         let data = json!({ "names": document_ids });
 
-        let _ = self
-            .delete("api/system/remove-documents", &data)
-            .await?
-            .error_for_status()?;
+        self.delete("api/system/remove-documents", &data).await?;
 
         Ok(())
     }
 
-    /// GET /api/workspace/{slug}
-    /// Get the documents in the workspace with the given slug
-    /// NOTE: This is not documented in the API so possibly unstable
-    pub async fn get_api_workspace_slug(&self, slug: &str) -> Result<(), LLMError> {
-        let url = format!("{}/api/workspace/{}", self.base_url, slug);
-        dbg!(&url);
-        let response = self.get(&url).await?.error_for_status()?;
+    /// GET /workspace/{slug}
+    ///
+    /// Get the documents embedded in the workspace with the given slug
+    pub async fn get_workspace_documents(
+        &self,
+        slug: &str,
+    ) -> Result<Vec<WorkspaceDocument>, LLMError> {
+        let workspace = self.get_workspace_by_slug(slug).await?;
 
-        let json = response.json::<Value>().await?;
+        Ok(workspace.documents.unwrap_or_default())
+    }
 
-        dbg!(json);
+    // helper functions ///////////////////////////////////////////////////////////////////////
 
-        // let documents: Vec<Document> = response
-        //     .local_files
-        //     .items
-        //     .unwrap_or_default()
-        //     .iter()
-        //     .flat_map(Self::extract_documents)
-        //     .collect();
+    // Extract the uploaded document from an upload response, relaying the server's error if the
+    // upload failed
+    fn document_from_response(
+        response: DocumentMultipartResponse,
+        name: &str,
+    ) -> Result<Document, LLMError> {
+        if !response.success {
+            return Err(LLMError::ServiceError(
+                response
+                    .error
+                    .unwrap_or_else(|| format!("upload failed for '{}'", name)),
+            ));
+        }
 
-        Ok(())
+        response
+            .documents
+            .first()
+            .map(Document::from)
+            .ok_or(LLMError::CustomError(
+                "Invalid response from server: no document returned".to_string(),
+            ))
     }
 
-    // helper functions ///////////////////////////////////////////////////////////////////////
-
     // documents_response is a nested list of folder and document types:
     // extract the documents from the response
     fn extract_documents(item: &Item) -> Vec<Document> {
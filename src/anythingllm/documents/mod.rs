@@ -2,12 +2,12 @@
 //!
 //! ## Example usage:
 //!
-//! ```rust
-//! use anythingllm::client::AnythingLLMClient;
+//! ```rust,no_run
+//! use ayda::anythingllm::client::AnythingLLMClient;
 //!
 //! #[tokio::main]
 //! async fn main() {
-//!     let client = AnythingLLMClient::new("127.0.0.1", "8080", "api_key");
+//!     let client = AnythingLLMClient::new("127.0.0.1", "8080", "api_key").unwrap();
 //!     let docs = client.get_documents().await.unwrap();
 //!     assert!(docs.len() > 0);
 //! }
@@ -43,7 +43,21 @@ pub struct DocumentsResponse {
     pub local_files: Item,
 }
 
+/// Metadata describing a raw text document.
+#[derive(Debug, serde::Serialize, Clone, Default)]
+pub struct RawTextMetadata {
+    pub title: String,
+    #[serde(rename = "docAuthor", skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "docSource", skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
 /// Structure to deserialize the response from the API.
+///
+/// Returned by both the multipart upload and the raw text endpoints.
 #[derive(Debug, serde::Deserialize)]
 pub struct DocumentMultipartResponse {
     pub documents: Vec<Item>,
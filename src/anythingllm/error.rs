@@ -1,13 +1,14 @@
 //! Custom error types for the `anythingllm` library
 
+use reqwest::StatusCode;
+use serde_json::Value;
+
 #[derive(thiserror::Error, Debug)]
 pub enum LLMError {
     #[error("Authentication error")]
     AuthError,
     #[error("Request error: {0}")]
     ReqwestError(#[from] reqwest::Error),
-    #[error("Bad request: {0}")]
-    BadRequest(String),
     #[error("Service error: {0}")]
     ServiceError(String),
     #[error("Workspace '{0}' not found")]
@@ -15,12 +16,39 @@ pub enum LLMError {
     #[error("Multiple workspaces with name {0} found")]
     MultipleWorkspacesError(String),
 
+    #[error("Authentication failed for '{endpoint}': {message}")]
+    Unauthorized { endpoint: String, message: String },
+    #[error("Not found '{endpoint}': {message}")]
+    NotFound { endpoint: String, message: String },
+    #[error("Invalid request to '{endpoint}': {message}")]
+    ValidationError { endpoint: String, message: String },
+    #[error("Payload too large for '{endpoint}': {message}")]
+    PayloadTooLarge { endpoint: String, message: String },
+    #[error("Server error {status} from '{endpoint}': {message}")]
+    ServerError {
+        status: u16,
+        endpoint: String,
+        message: String,
+    },
+    #[error("HTTP error {status} from '{endpoint}': {message}")]
+    HttpError {
+        status: u16,
+        endpoint: String,
+        message: String,
+    },
+
+    #[error("Thread '{0}' not found")]
+    ThreadNotFoundError(String),
+    #[error("Multiple threads with name {0} found")]
+    MultipleThreadsError(String),
     #[error("Document exists: {0}")]
     DocumentExistsError(String),
     #[error("Document not found on filesystem")]
     FileSystemError(#[from] std::io::Error),
     #[error("Document not found in workspace: {0}")]
     DocumentNotFoundWorkspaceError(String),
+    #[error("Multiple documents matching '{0}' found in workspace")]
+    MultipleDocumentsError(String),
     #[error("Failed to load PDF")]
     PDFLoadError(#[from] lopdf::Error),
     #[error("Multipart form encoding error: {0}")]
@@ -28,6 +56,12 @@ pub enum LLMError {
     #[error("File too large")]
     FileTooLarge,
 
+    #[error("Invalid configuration: {0}")]
+    ConfigError(String),
+
+    #[error("Not supported by this backend: {0}")]
+    UnsupportedError(String),
+
     #[error("Custom error: {0}")]
     CustomError(String),
     #[error("Unhandled error: {0}")]
@@ -36,3 +70,97 @@ pub enum LLMError {
     #[error("Cancelled")]
     Cancelled,
 }
+
+impl LLMError {
+    /// Map an unsuccessful response onto an error carrying the server's message.
+    ///
+    /// The body may be JSON with an `error` or `message` field, or plain text.
+    pub fn from_response(status: StatusCode, endpoint: &str, body: &str) -> Self {
+        let endpoint = endpoint.to_string();
+        let message = Self::message_from_body(body).unwrap_or_else(|| {
+            status
+                .canonical_reason()
+                .unwrap_or("no message")
+                .to_string()
+        });
+
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                LLMError::Unauthorized { endpoint, message }
+            }
+            StatusCode::NOT_FOUND => LLMError::NotFound { endpoint, message },
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => {
+                LLMError::ValidationError { endpoint, message }
+            }
+            StatusCode::PAYLOAD_TOO_LARGE => LLMError::PayloadTooLarge { endpoint, message },
+            s if s.is_server_error() => LLMError::ServerError {
+                status: s.as_u16(),
+                endpoint,
+                message,
+            },
+            s => LLMError::HttpError {
+                status: s.as_u16(),
+                endpoint,
+                message,
+            },
+        }
+    }
+
+    fn message_from_body(body: &str) -> Option<String> {
+        let body = body.trim();
+        if body.is_empty() {
+            return None;
+        }
+
+        match serde_json::from_str::<Value>(body) {
+            Ok(Value::Object(map)) => ["error", "message"]
+                .iter()
+                .find_map(|key| map.get(*key).and_then(Value::as_str))
+                .map(str::to_string)
+                .or_else(|| Some(body.to_string())),
+            Ok(Value::String(message)) => Some(message),
+            _ => Some(body.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use crate::anythingllm::error::LLMError;
+
+    #[test]
+    fn test_from_response_json_error() {
+        let error = LLMError::from_response(
+            StatusCode::NOT_FOUND,
+            "workspace/missing",
+            r#"{"error":"Workspace not found"}"#,
+        );
+
+        assert!(matches!(
+            error,
+            LLMError::NotFound { ref message, .. } if message == "Workspace not found"
+        ));
+    }
+
+    #[test]
+    fn test_from_response_text_error() {
+        let error = LLMError::from_response(StatusCode::BAD_GATEWAY, "workspaces", "upstream down");
+
+        assert!(matches!(
+            error,
+            LLMError::ServerError { status: 502, ref message, .. } if message == "upstream down"
+        ));
+    }
+
+    #[test]
+    fn test_from_response_empty_body() {
+        let error = LLMError::from_response(StatusCode::UNAUTHORIZED, "auth", "");
+
+        assert!(matches!(
+            error,
+            LLMError::Unauthorized { ref message, .. } if message == "Unauthorized"
+        ));
+    }
+}
@@ -0,0 +1,211 @@
+//! Concurrency and rate limits for requests to the AnythingLLM server.
+//!
+//! Requests are grouped into endpoint classes, each with its own maximum number of requests in
+//! flight, so that a large import can't flood the server with uploads while chats are waiting.
+//! An optional cap on the number of requests per second applies across all classes.
+//!
+//! The limiter is shared by every clone of a client, so concurrent tasks that use the same client
+//! are limited together.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+/// A group of endpoints that share a concurrency limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointClass {
+    /// Document uploads, which the server converts and stores.
+    Upload,
+    /// Embedding documents in a workspace, which vectorizes them.
+    Embedding,
+    /// Chat messages, which run the LLM.
+    Chat,
+    /// Everything else.
+    Other,
+}
+
+impl EndpointClass {
+    /// The class of an endpoint path such as `workspace/{slug}/chat`.
+    pub fn of(endpoint: &str) -> Self {
+        if endpoint.starts_with("document/upload") || endpoint == "document/raw-text" {
+            EndpointClass::Upload
+        } else if endpoint.ends_with("/update-embeddings") {
+            EndpointClass::Embedding
+        } else if endpoint.ends_with("/chat") || endpoint.ends_with("/stream-chat") {
+            EndpointClass::Chat
+        } else {
+            EndpointClass::Other
+        }
+    }
+}
+
+/// The maximum number of requests in flight for each endpoint class, and an optional cap on
+/// requests per second.
+///
+/// The defaults suit the AnythingLLM desktop app running on the same machine.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    pub uploads: usize,
+    pub embeddings: usize,
+    pub chats: usize,
+    pub other: usize,
+    pub requests_per_second: Option<f64>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            uploads: 4,
+            embeddings: 1,
+            chats: 2,
+            other: 8,
+            requests_per_second: None,
+        }
+    }
+}
+
+impl Limits {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Enforces `Limits` across all the requests made with a client.
+#[derive(Debug)]
+pub struct Limiter {
+    limits: Limits,
+    uploads: Arc<Semaphore>,
+    embeddings: Arc<Semaphore>,
+    chats: Arc<Semaphore>,
+    other: Arc<Semaphore>,
+    interval: Option<Duration>,
+    next_slot: Mutex<Instant>,
+}
+
+impl Limiter {
+    pub fn new(limits: &Limits) -> Self {
+        let semaphore = |n: usize| Arc::new(Semaphore::new(n.max(1)));
+
+        Self {
+            limits: limits.clone(),
+            uploads: semaphore(limits.uploads),
+            embeddings: semaphore(limits.embeddings),
+            chats: semaphore(limits.chats),
+            other: semaphore(limits.other),
+            interval: limits
+                .requests_per_second
+                .filter(|rps| *rps > 0.0)
+                .map(|rps| Duration::from_secs_f64(1.0 / rps)),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// The limits this limiter enforces.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Wait until a request of this class may be sent. The request may be in flight until the
+    /// returned permit is dropped.
+    pub async fn acquire(&self, class: EndpointClass) -> OwnedSemaphorePermit {
+        let semaphore = match class {
+            EndpointClass::Upload => &self.uploads,
+            EndpointClass::Embedding => &self.embeddings,
+            EndpointClass::Chat => &self.chats,
+            EndpointClass::Other => &self.other,
+        };
+        let permit = semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("limiter semaphore closed");
+
+        if let Some(interval) = self.interval {
+            let mut next_slot = self.next_slot.lock().await;
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + interval;
+            drop(next_slot);
+
+            tokio::time::sleep_until(slot).await;
+        }
+
+        permit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use futures::future::join_all;
+
+    use super::{EndpointClass, Limiter, Limits};
+
+    #[test]
+    fn test_endpoint_class() {
+        assert_eq!(EndpointClass::of("document/upload"), EndpointClass::Upload);
+        assert_eq!(
+            EndpointClass::of("document/upload-link"),
+            EndpointClass::Upload
+        );
+        assert_eq!(
+            EndpointClass::of("workspace/climate/update-embeddings"),
+            EndpointClass::Embedding
+        );
+        assert_eq!(
+            EndpointClass::of("workspace/climate/thread/t1/stream-chat"),
+            EndpointClass::Chat
+        );
+        assert_eq!(
+            EndpointClass::of("workspace/climate/chats"),
+            EndpointClass::Other
+        );
+    }
+
+    #[tokio::test]
+    async fn test_limits_requests_in_flight() {
+        let limiter = Arc::new(Limiter::new(&Limits {
+            uploads: 2,
+            ..Default::default()
+        }));
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+
+        let tasks = (0..6).map(|_| {
+            let limiter = limiter.clone();
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            async move {
+                let _permit = limiter.acquire(EndpointClass::Upload).await;
+                let n = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(n, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+            }
+        });
+        join_all(tasks).await;
+
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_limits_requests_per_second() {
+        let limiter = Limiter::new(&Limits {
+            requests_per_second: Some(50.0),
+            ..Default::default()
+        });
+        let start = tokio::time::Instant::now();
+
+        for _ in 0..5 {
+            let _permit = limiter.acquire(EndpointClass::Other).await;
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(80));
+    }
+}
@@ -5,9 +5,15 @@
 //!
 
 pub use documents::*;
+pub use system::*;
+pub use thread::*;
 pub use workspace::*;
 
 pub mod client;
 pub mod documents;
 pub mod error;
+pub mod limiter;
+pub mod retry;
+pub mod system;
+pub mod thread;
 pub mod workspace;
@@ -0,0 +1,163 @@
+//! Retry policy for requests to the AnythingLLM server.
+//!
+//! Transient failures (connection errors, timeouts, `5xx` and `429` responses) are retried with
+//! exponential backoff and jitter, up to a maximum number of attempts. A `Retry-After` header on
+//! the response takes precedence over the computed backoff, but `RetryPolicy::delay` gives the
+//! request up if the server asks to wait longer than the policy's `max_backoff`.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Response, StatusCode};
+use serde::{Deserialize, Serialize};
+
+/// Controls how failed requests are retried.
+///
+/// In the configuration file the delays are given in milliseconds, e.g.
+/// `{ "max_attempts": 6, "max_backoff_ms": 60000 }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first. `1` disables retries.
+    pub max_attempts: u32,
+    /// The delay before the first retry.
+    #[serde(rename = "initial_backoff_ms", with = "millis")]
+    pub initial_backoff: Duration,
+    /// The upper bound on any single delay.
+    #[serde(rename = "max_backoff_ms", with = "millis")]
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The delay before retrying after `attempt` (counting from 1) has failed, given the delay
+    /// the server requested in a `Retry-After` header, if any.
+    ///
+    /// Returns `None` if the server asked to wait longer than `max_backoff`, and the request
+    /// should be given up rather than hold up the caller.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        match retry_after {
+            Some(delay) if delay > self.max_backoff => None,
+            Some(delay) => Some(delay),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+
+    /// The delay before retrying after `attempt` (counting from 1) has failed.
+    ///
+    /// The delay doubles with every attempt up to `max_backoff`, and a random jitter of up to half
+    /// the delay is subtracted so that concurrent requests don't retry in lockstep.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let delay = self
+            .initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff);
+        let jitter = rand::thread_rng().gen_range(0.0..=0.5);
+
+        delay.mul_f64(1.0 - jitter)
+    }
+
+    /// Return true if the request error is worth retrying.
+    pub fn is_retryable_error(error: &reqwest::Error) -> bool {
+        error.is_connect() || error.is_timeout()
+    }
+
+    /// Return true if the response status is worth retrying.
+    pub fn is_retryable_status(status: StatusCode) -> bool {
+        status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+    }
+
+    /// The delay requested by the server in a `Retry-After` header, if any.
+    ///
+    /// The header may be either a number of seconds or an HTTP date.
+    pub fn retry_after(response: &Response) -> Option<Duration> {
+        let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
+        }
+
+        let date: DateTime<Utc> = DateTime::parse_from_rfc2822(value).ok()?.into();
+        (date - Utc::now()).to_std().ok()
+    }
+}
+
+// (De)serialize a duration as a whole number of milliseconds
+mod millis {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_millis(u64::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::anythingllm::retry::RetryPolicy;
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(1000),
+        };
+
+        let first = policy.backoff(1);
+        assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+
+        let third = policy.backoff(3);
+        assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+
+        let tenth = policy.backoff(10);
+        assert!(tenth >= Duration::from_millis(500) && tenth <= Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn test_long_retry_after_gives_up() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+        };
+
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_secs(5))),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(3600))), None);
+        assert!(policy.delay(1, None).is_some());
+    }
+}
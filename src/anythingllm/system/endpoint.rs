@@ -0,0 +1,29 @@
+use crate::anythingllm::client::AnythingLLMClient;
+use crate::anythingllm::error::LLMError;
+use crate::anythingllm::system::{GetSystemResponse, GetSystemVectorCountResponse, SystemSettings};
+
+impl AnythingLLMClient {
+    /// GET /system
+    pub async fn get_system(&self) -> Result<SystemSettings, LLMError> {
+        let response = self
+            .get("system")
+            .await?
+            .json::<GetSystemResponse>()
+            .await?;
+
+        Ok(response.settings)
+    }
+
+    /// GET /system/vector-count
+    ///
+    /// The total number of vectors in the vector database, across all workspaces.
+    pub async fn get_system_vector_count(&self) -> Result<u64, LLMError> {
+        let response = self
+            .get("system/vector-count")
+            .await?
+            .json::<GetSystemVectorCountResponse>()
+            .await?;
+
+        Ok(response.vector_count)
+    }
+}
@@ -0,0 +1,9 @@
+//! `System` models and endpoints for the `anythingllm` client.
+//!
+
+#[allow(unused_imports)]
+pub use endpoint::*;
+pub use models::*;
+
+mod endpoint;
+mod models;
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+/// Represents the system settings of an AnythingLLM instance.
+///
+/// The settings that name the LLM model depend on the provider, so any settings not modelled
+/// explicitly are kept in `other`.
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct SystemSettings {
+    #[serde(rename = "LLMProvider")]
+    pub llm_provider: Option<String>,
+    #[serde(rename = "EmbeddingEngine")]
+    pub embedding_engine: Option<String>,
+    #[serde(rename = "EmbeddingModelPref")]
+    pub embedding_model: Option<String>,
+    #[serde(rename = "VectorDB")]
+    pub vector_db: Option<String>,
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
+}
+
+impl SystemSettings {
+    /// The model used by the LLM provider, if the instance reports it.
+    ///
+    /// Providers store their model under keys such as `OpenAiModelPref` or `OllamaLLMModelPref`.
+    /// The settings keep the preferences of providers that were used before, so only the key of
+    /// the current provider is read, and there is no model if it has none.
+    pub fn llm_model(&self) -> Option<String> {
+        let mut keys: Vec<_> = self
+            .other
+            .keys()
+            .filter(|k| k.ends_with("ModelPref") && !k.contains("Embedding"))
+            .collect();
+        keys.sort();
+
+        let provider = self
+            .llm_provider
+            .as_deref()
+            .unwrap_or_default()
+            .to_lowercase();
+        if provider.is_empty() {
+            return None;
+        }
+        let key = keys
+            .iter()
+            .find(|k| k.to_lowercase().starts_with(&provider))?;
+
+        self.other.get(*key)?.as_str().map(str::to_string)
+    }
+}
+
+/// Structure to deserialize the response from the API.
+#[derive(Debug, serde::Deserialize)]
+pub struct GetSystemResponse {
+    pub settings: SystemSettings,
+}
+
+/// Structure to deserialize the response from the API.
+#[derive(Debug, serde::Deserialize)]
+pub struct GetSystemVectorCountResponse {
+    #[serde(rename = "vectorCount")]
+    pub vector_count: u64,
+}
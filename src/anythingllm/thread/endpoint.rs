@@ -0,0 +1,159 @@
+use futures::Stream;
+use reqwest::Method;
+use serde_json::json;
+
+use crate::anythingllm::client::AnythingLLMClient;
+use crate::anythingllm::error::LLMError;
+use crate::anythingllm::thread::{PostThreadResponse, Thread};
+use crate::anythingllm::workspace::{
+    ChatMode, GetWorkspaceChatsResponse, PostWorkspaceSlugChatResponse, StreamChatChunk,
+    WorkspaceChat,
+};
+
+impl AnythingLLMClient {
+    /// POST /workspace/{slug}/thread/new
+    pub async fn create_thread(&self, slug: &str, name: &str) -> Result<Thread, LLMError> {
+        let url = format!("{}/{}/{}", "workspace", slug, "thread/new");
+
+        let response = self
+            .post(&url, &json!({ "name": name }))
+            .await?
+            .json::<PostThreadResponse>()
+            .await?;
+
+        Self::thread_from_response(response)
+    }
+
+    /// Get the threads of a workspace.
+    pub async fn get_threads(&self, slug: &str) -> Result<Vec<Thread>, LLMError> {
+        let workspace = self.get_workspace_by_slug(slug).await?;
+
+        Ok(workspace.threads.unwrap_or_default())
+    }
+
+    /// Return the thread in the workspace with name `thread_name`.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_thread_by_name(
+        &self,
+        slug: &str,
+        thread_name: &str,
+    ) -> Result<Thread, LLMError> {
+        let threads = self.get_threads(slug).await?;
+        let matching_threads: Vec<_> = threads.iter().filter(|t| t.name == thread_name).collect();
+
+        match matching_threads.len() {
+            0 => Err(LLMError::ThreadNotFoundError(thread_name.to_string())),
+            1 => Ok(matching_threads[0].clone()),
+            _ => Err(LLMError::MultipleThreadsError(thread_name.to_string())),
+        }
+    }
+
+    /// POST /workspace/{slug}/thread/{thread_slug}/update
+    pub async fn rename_thread(
+        &self,
+        slug: &str,
+        thread_slug: &str,
+        name: &str,
+    ) -> Result<Thread, LLMError> {
+        let url = format!(
+            "{}/{}/{}/{}/{}",
+            "workspace", slug, "thread", thread_slug, "update"
+        );
+
+        let response = self
+            .post(&url, &json!({ "name": name }))
+            .await?
+            .json::<PostThreadResponse>()
+            .await?;
+
+        Self::thread_from_response(response)
+    }
+
+    /// DELETE /workspace/{slug}/thread/{thread_slug}
+    pub async fn delete_thread(&self, slug: &str, thread_slug: &str) -> Result<(), LLMError> {
+        let endpoint = format!("{}/{}/{}/{}", "workspace", slug, "thread", thread_slug);
+        let url = format!("{}/{}", self.base_url_api_v1, endpoint);
+        let response = self
+            .send_with_retry(&endpoint, || async {
+                Ok(self.request(Method::DELETE, &url))
+            })
+            .await?;
+
+        Self::response_text(&endpoint, response).await?;
+
+        Ok(())
+    }
+
+    /// GET /workspace/{slug}/thread/{thread_slug}/chats
+    ///
+    /// Get the chat history of a thread, oldest message first.
+    pub async fn get_thread_chats(
+        &self,
+        slug: &str,
+        thread_slug: &str,
+    ) -> Result<Vec<WorkspaceChat>, LLMError> {
+        let url = format!(
+            "{}/{}/{}/{}/{}",
+            "workspace", slug, "thread", thread_slug, "chats"
+        );
+
+        let response = self
+            .get(&url)
+            .await?
+            .json::<GetWorkspaceChatsResponse>()
+            .await?;
+
+        Ok(response.history)
+    }
+
+    /// POST /workspace/{slug}/thread/{thread_slug}/chat
+    pub async fn post_thread_chat(
+        &self,
+        slug: &str,
+        thread_slug: &str,
+        message: &str,
+        mode: &ChatMode,
+    ) -> Result<PostWorkspaceSlugChatResponse, LLMError> {
+        let url = format!(
+            "{}/{}/{}/{}/{}",
+            "workspace", slug, "thread", thread_slug, "chat"
+        );
+        let json = Self::chat_body(message, mode);
+
+        let response = self
+            .post(&url, &json)
+            .await?
+            .json::<PostWorkspaceSlugChatResponse>()
+            .await?;
+
+        Ok(response)
+    }
+
+    /// POST /workspace/{slug}/thread/{thread_slug}/stream-chat
+    ///
+    /// Stream the response to a chat message in a thread as it is generated.
+    pub async fn stream_thread_chat(
+        &self,
+        slug: &str,
+        thread_slug: &str,
+        message: &str,
+        mode: &ChatMode,
+    ) -> Result<impl Stream<Item = Result<StreamChatChunk, LLMError>>, LLMError> {
+        let url = format!(
+            "{}/{}/{}/{}/{}",
+            "workspace", slug, "thread", thread_slug, "stream-chat"
+        );
+
+        self.stream_chat(&url, message, mode).await
+    }
+
+    // helper functions ///////////////////////////////////////////////////////////////////////
+
+    fn thread_from_response(response: PostThreadResponse) -> Result<Thread, LLMError> {
+        response.thread.ok_or(LLMError::ServiceError(
+            response
+                .message
+                .unwrap_or_else(|| "expected struct Thread, got null".to_string()),
+        ))
+    }
+}
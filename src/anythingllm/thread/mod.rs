@@ -0,0 +1,11 @@
+//! `Thread` models and endpoints for the `anythingllm` client.
+//!
+//! A thread is a named conversation within a workspace, with its own chat history.
+//!
+
+#[allow(unused_imports)]
+pub use endpoint::*;
+pub use models::*;
+
+mod endpoint;
+mod models;
@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+
+/// Represents a thread in a workspace.
+///
+/// The threads listed with a workspace only carry their name and slug.
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct Thread {
+    pub id: Option<u32>,
+    pub name: String,
+    pub slug: String,
+    pub workspace_id: Option<u32>,
+    pub user_id: Option<u32>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(rename = "lastUpdatedAt")]
+    pub last_updated_at: Option<DateTime<Utc>>,
+}
+
+/// Structure to deserialize the response from the API.
+#[derive(Debug, serde::Deserialize)]
+pub struct PostThreadResponse {
+    pub thread: Option<Thread>,
+    pub message: Option<String>,
+}
@@ -1,9 +1,15 @@
+use std::fmt;
+use std::str::FromStr;
+
+use futures::{Stream, StreamExt};
+use reqwest::Method;
 use serde::de::Error;
-use serde::{Deserialize, Deserializer};
-use serde_json::json;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
 
 use crate::anythingllm::client::AnythingLLMClient;
 use crate::anythingllm::error::LLMError;
+use crate::anythingllm::limiter::EndpointClass;
 use crate::anythingllm::workspace::*;
 use crate::app::commands::workspace::import::UpdateParameter;
 
@@ -16,31 +22,61 @@ pub enum ChatMode {
     Chat,
 }
 
+impl FromStr for ChatMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chat" => Ok(ChatMode::Chat),
+            "query" => Ok(ChatMode::Query),
+            _ => Err(format!("Invalid value for ChatMode: '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for ChatMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChatMode::Chat => write!(f, "chat"),
+            ChatMode::Query => write!(f, "query"),
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for ChatMode {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
         let s: String = Deserialize::deserialize(deserializer)?;
-        match s.as_str() {
-            "chat" => Ok(ChatMode::Chat),
-            "query" => Ok(ChatMode::Query),
-            _ => Err(D::Error::custom("Invalid value for ChatMode")),
-        }
+        s.parse().map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for ChatMode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
     }
 }
 
 impl AnythingLLMClient {
     /// POST /workspace/new
     pub async fn create_workspace(&self, name: &str) -> Result<Workspace, LLMError> {
+        let endpoint = "workspace/new";
+        let _permit = self.limiter.acquire(EndpointClass::of(endpoint)).await;
         let response = self
-            .client
-            .post(&format!("{}/{}", self.base_url_api_v1, "workspace/new"))
+            .request(
+                Method::POST,
+                &format!("{}/{}", self.base_url_api_v1, endpoint),
+            )
             .header("Content-Type", "application/json")
             .body(json!({ "name": name }).to_string())
             .send()
-            .await?
-            .error_for_status()?;
+            .await?;
+        let response = Self::check_status(endpoint, response).await?;
 
         let workspace_new_response = response.json::<GetWorkspaceNewResponse>().await?;
 
@@ -49,7 +85,7 @@ impl AnythingLLMClient {
 
     /// GET /workspaces
     pub async fn get_workspaces(&self) -> Result<Vec<Workspace>, LLMError> {
-        let response = self.get("workspaces").await?.error_for_status()?;
+        let response = self.get("workspaces").await?;
 
         let workspaces_response = response.json::<GetWorkspacesResponse>().await?;
 
@@ -60,10 +96,7 @@ impl AnythingLLMClient {
     pub async fn get_workspace_by_slug(&self, slug: &str) -> Result<Workspace, LLMError> {
         let url = format!("{}/{}", "workspace", slug);
 
-        let response = match self.get(&url).await {
-            Ok(response) => response,
-            Err(e) => return Err(LLMError::ServiceError(e.to_string())),
-        };
+        let response = self.get(&url).await?;
 
         let workspace = response.json::<GetWorkspaceSlugResponse>().await?;
 
@@ -90,6 +123,65 @@ impl AnythingLLMClient {
         }
     }
 
+    /// Return the document in the workspace whose title or filename matches `document_name`
+    ///
+    /// An exact (case insensitive) match on the title or filename is preferred. Otherwise the
+    /// name must be contained in exactly one document's title or filename.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_workspace_document_by_name(
+        &self,
+        slug: &str,
+        document_name: &str,
+    ) -> Result<WorkspaceDocument, LLMError> {
+        let documents = self
+            .get_workspace_by_slug(slug)
+            .await?
+            .documents
+            .unwrap_or_default();
+
+        let name = document_name.to_lowercase();
+        let title = |d: &WorkspaceDocument| d.metadata.title.to_lowercase();
+        let filename = |d: &WorkspaceDocument| d.filename.to_lowercase();
+
+        let exact: Vec<_> = documents
+            .iter()
+            .filter(|d| title(d) == name || filename(d) == name)
+            .collect();
+        let matching_documents = match exact.is_empty() {
+            true => documents
+                .iter()
+                .filter(|d| title(d).contains(&name) || filename(d).contains(&name))
+                .collect(),
+            false => exact,
+        };
+
+        match matching_documents.len() {
+            0 => Err(LLMError::DocumentNotFoundWorkspaceError(
+                document_name.to_string(),
+            )),
+            1 => Ok(matching_documents[0].clone()),
+            _ => Err(LLMError::MultipleDocumentsError(document_name.to_string())),
+        }
+    }
+
+    /// POST /workspace/{slug}/update-pin
+    ///
+    /// Pin or unpin a document in a workspace. A pinned document is included in full in the
+    /// context of every chat.
+    pub async fn update_pin(
+        &self,
+        slug: &str,
+        docpath: &str,
+        pinned: bool,
+    ) -> Result<(), LLMError> {
+        let url = format!("{}/{}/{}", "workspace", slug, "update-pin");
+        let json = json!({ "docPath": docpath, "pinStatus": pinned });
+
+        self.post(&url, &json).await?;
+
+        Ok(())
+    }
+
     /// DELETE /workspace/{slug}
     ///
     /// Delete a workspace by its slug and remove all embedded documents from the file system.
@@ -108,23 +200,41 @@ impl AnythingLLMClient {
             .unwrap();
 
         // delete the workspace
-        let url = format!("{}/{}/{}", self.base_url_api_v1, "workspace", slug);
-        let response = match self.client.delete(&url).send().await {
-            Ok(response) => response,
-            Err(e) => return Err(LLMError::ServiceError(e.to_string())),
-        };
-
-        // NOTE: For a bad request, the API returns a "200 OK" status with the text "Bad Request"
-        // not a "400 Bad Request"
-        let response_text = response.text().await?;
+        let endpoint = format!("{}/{}", "workspace", slug);
+        let url = format!("{}/{}", self.base_url_api_v1, endpoint);
+        let response = self
+            .send_with_retry(&endpoint, || async {
+                Ok(self.request(Method::DELETE, &url))
+            })
+            .await?;
 
-        if response_text == "Bad Request" {
-            return Err(LLMError::BadRequest(url));
-        }
+        Self::response_text(&endpoint, response).await?;
 
         Ok(())
     }
 
+    /// POST /workspace/{slug}/update
+    ///
+    /// Update the settings of a workspace. Settings that are `None` are left unchanged.
+    pub async fn update_workspace_settings(
+        &self,
+        slug: &str,
+        settings: &WorkspaceSettings,
+    ) -> Result<Workspace, LLMError> {
+        let url = format!("{}/{}/{}", "workspace", slug, "update");
+        let json = serde_json::to_value(settings).unwrap();
+
+        let response = self
+            .post(&url, &json)
+            .await?
+            .json::<GetWorkspaceSlugResponse>()
+            .await?;
+
+        response.workspace.ok_or(LLMError::CustomError(
+            "Invalid response from server: expected struct Workspace, got null".to_string(),
+        ))
+    }
+
     /// POST /workspace/{slug}/update-embeddings
     pub async fn update_embeddings(
         &self,
@@ -138,14 +248,7 @@ impl AnythingLLMClient {
             UpdateParameter::Deletes => json!({ "deletes": docs }),
         };
 
-        let response = self
-            .post(&url, &json)
-            .await
-            .map_err(|e| LLMError::ServiceError(e.to_string()))?;
-
-        if !response.status().is_success() {
-            return Err(LLMError::ServiceError(url));
-        }
+        self.post(&url, &json).await?;
 
         Ok(())
     }
@@ -158,21 +261,85 @@ impl AnythingLLMClient {
         mode: &ChatMode,
     ) -> Result<PostWorkspaceSlugChatResponse, LLMError> {
         let url = format!("{}/{}/{}", "workspace", slug, "chat");
-        let json = match mode {
-            ChatMode::Query => json!({ "message": message, "mode": "query" }),
-            ChatMode::Chat => json!({ "message": message, "mode": "chat" }),
-        };
+        let json = Self::chat_body(message, mode);
 
         let response = self
             .post(&url, &json)
             .await?
-            .error_for_status()?
             .json::<PostWorkspaceSlugChatResponse>()
             .await?;
 
         Ok(response)
     }
 
+    /// POST /workspace/{slug}/stream-chat
+    ///
+    /// Stream the response to a chat message as it is generated. The server replies with
+    /// server-sent events, which are decoded into `StreamChatChunk`s.
+    pub async fn stream_workspace_slug_chat(
+        &self,
+        slug: &str,
+        message: &str,
+        mode: &ChatMode,
+    ) -> Result<impl Stream<Item = Result<StreamChatChunk, LLMError>>, LLMError> {
+        let url = format!("{}/{}/{}", "workspace", slug, "stream-chat");
+
+        self.stream_chat(&url, message, mode).await
+    }
+
+    // Post a chat message to a streaming chat endpoint and decode the server-sent events
+    pub(crate) async fn stream_chat(
+        &self,
+        endpoint: &str,
+        message: &str,
+        mode: &ChatMode,
+    ) -> Result<impl Stream<Item = Result<StreamChatChunk, LLMError>>, LLMError> {
+        let json = Self::chat_body(message, mode);
+
+        // the permit is held by the stream, so the chat counts against the limits until the
+        // whole response has been read or the stream is dropped
+        let (response, permit) = self.post_stream(endpoint, &json).await?;
+
+        // the end of the body is marked with `None` so that a last event the server did not
+        // terminate with a blank line is still parsed
+        let chunks = response
+            .bytes_stream()
+            .map(Some)
+            .chain(futures::stream::once(futures::future::ready(None)))
+            .scan((Vec::<u8>::new(), permit), |(buffer, _permit), bytes| {
+                let chunks: Vec<Result<StreamChatChunk, LLMError>> = match bytes {
+                    Some(Ok(bytes)) => {
+                        buffer.extend(bytes.iter().filter(|b| **b != b'\r'));
+                        Self::parse_sse_frames(Self::drain_sse_frames(buffer))
+                    }
+                    Some(Err(e)) => vec![Err(e.into())],
+                    None => {
+                        buffer.extend(b"\n\n");
+                        Self::parse_sse_frames(Self::drain_sse_frames(buffer))
+                    }
+                };
+                futures::future::ready(Some(chunks))
+            })
+            .flat_map(futures::stream::iter);
+
+        Ok(chunks)
+    }
+
+    /// GET /workspace/{slug}/chats
+    ///
+    /// Get the chat history of a workspace, oldest message first.
+    pub async fn get_workspace_chats(&self, slug: &str) -> Result<Vec<WorkspaceChat>, LLMError> {
+        let url = format!("{}/{}/{}", "workspace", slug, "chats");
+
+        let response = self
+            .get(&url)
+            .await?
+            .json::<GetWorkspaceChatsResponse>()
+            .await?;
+
+        Ok(response.history)
+    }
+
     /// Remove all workspaces
     pub async fn delete_all_workspaces(&self) -> Result<(), LLMError> {
         let workspaces = self.get_workspaces().await?;
@@ -181,4 +348,89 @@ impl AnythingLLMClient {
         }
         Ok(())
     }
+
+    // helper functions ///////////////////////////////////////////////////////////////////////
+
+    pub(crate) fn chat_body(message: &str, mode: &ChatMode) -> Value {
+        json!({ "message": message, "mode": mode })
+    }
+
+    fn parse_sse_frames(frames: Vec<String>) -> Vec<Result<StreamChatChunk, LLMError>> {
+        frames
+            .iter()
+            .flat_map(|data| match serde_json::from_str::<StreamChatFrame>(data) {
+                Ok(frame) => Vec::<StreamChatChunk>::from(frame)
+                    .into_iter()
+                    .map(Ok)
+                    .collect(),
+                Err(e) => vec![Err(LLMError::ServiceError(format!(
+                    "invalid stream frame: {}",
+                    e
+                )))],
+            })
+            .collect()
+    }
+
+    // Remove every complete server-sent event from the buffer and return its `data` payload.
+    // Incomplete events are left in the buffer until more bytes arrive.
+    fn drain_sse_frames(buffer: &mut Vec<u8>) -> Vec<String> {
+        let mut frames = Vec::new();
+
+        while let Some(end) = buffer.windows(2).position(|w| w == b"\n\n") {
+            let event: Vec<u8> = buffer.drain(..end + 2).collect();
+            let event = String::from_utf8_lossy(&event);
+
+            let data = event
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(str::trim_start)
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            if !data.is_empty() {
+                frames.push(data);
+            }
+        }
+
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::anythingllm::client::AnythingLLMClient;
+    use crate::anythingllm::workspace::{StreamChatChunk, StreamChatFrame};
+
+    #[test]
+    fn test_drain_sse_frames_keeps_partial_event() {
+        let mut buffer = b"data: {\"a\":1}\n\ndata: {\"b\"".to_vec();
+        let frames = AnythingLLMClient::drain_sse_frames(&mut buffer);
+
+        assert_eq!(frames, vec![r#"{"a":1}"#.to_string()]);
+        assert_eq!(buffer, br#"data: {"b""#.to_vec());
+    }
+
+    #[test]
+    fn test_stream_chat_frame_into_chunks() {
+        let frame: StreamChatFrame = serde_json::from_str(
+            r#"{"uuid":"1","type":"textResponseChunk","textResponse":"Hello","sources":[],"close":true,"error":false}"#,
+        )
+        .unwrap();
+        let chunks = Vec::<StreamChatChunk>::from(frame);
+
+        assert!(matches!(&chunks[0], StreamChatChunk::Text(t) if t == "Hello"));
+        assert!(matches!(chunks[1], StreamChatChunk::Close));
+    }
+
+    #[test]
+    fn test_stream_chat_frame_abort_is_error() {
+        let frame: StreamChatFrame = serde_json::from_str(
+            r#"{"type":"abort","textResponse":null,"sources":[],"close":true,"error":"No workspace"}"#,
+        )
+        .unwrap();
+        let chunks = Vec::<StreamChatChunk>::from(frame);
+
+        assert_eq!(chunks.len(), 1);
+        assert!(matches!(&chunks[0], StreamChatChunk::Error(e) if e == "No workspace"));
+    }
 }
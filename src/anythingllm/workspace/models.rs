@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::anythingllm::thread::Thread;
 use crate::anythingllm::workspace::ChatMode;
 
 /// Represents a workspace.
@@ -17,7 +18,63 @@ pub struct Workspace {
     pub similarity_threshold: f64,
     #[serde(rename = "chatMode")]
     pub chat_mode: ChatMode,
+    #[serde(rename = "openAiTemp")]
+    pub temperature: Option<f64>,
+    #[serde(rename = "openAiHistory")]
+    pub history_length: Option<u32>,
+    #[serde(rename = "openAiPrompt")]
+    pub system_prompt: Option<String>,
+    #[serde(rename = "topN")]
+    pub top_n: Option<u32>,
     pub documents: Option<Vec<WorkspaceDocument>>,
+    pub threads: Option<Vec<Thread>>,
+}
+
+/// The user-adjustable settings of a workspace.
+///
+/// Used to update a workspace: settings that are `None` are left unchanged.
+#[derive(Debug, Serialize, serde::Deserialize, Clone, Default)]
+pub struct WorkspaceSettings {
+    #[serde(
+        rename = "similarityThreshold",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub similarity_threshold: Option<f64>,
+    #[serde(rename = "openAiTemp", skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(rename = "openAiHistory", skip_serializing_if = "Option::is_none")]
+    pub history_length: Option<u32>,
+    #[serde(rename = "topN", skip_serializing_if = "Option::is_none")]
+    pub top_n: Option<u32>,
+    #[serde(rename = "openAiPrompt", skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    #[serde(rename = "chatMode", skip_serializing_if = "Option::is_none")]
+    pub chat_mode: Option<ChatMode>,
+}
+
+impl WorkspaceSettings {
+    /// Return true if no setting is to be changed.
+    pub fn is_empty(&self) -> bool {
+        self.similarity_threshold.is_none()
+            && self.temperature.is_none()
+            && self.history_length.is_none()
+            && self.top_n.is_none()
+            && self.system_prompt.is_none()
+            && self.chat_mode.is_none()
+    }
+}
+
+impl From<&Workspace> for WorkspaceSettings {
+    fn from(workspace: &Workspace) -> Self {
+        WorkspaceSettings {
+            similarity_threshold: Some(workspace.similarity_threshold),
+            temperature: workspace.temperature,
+            history_length: workspace.history_length,
+            top_n: workspace.top_n,
+            system_prompt: workspace.system_prompt.clone(),
+            chat_mode: Some(workspace.chat_mode.clone()),
+        }
+    }
 }
 
 /// Represents a workspace document.
@@ -40,7 +97,8 @@ pub struct WorkspaceDocument {
 }
 
 /// Represents the metadata of a workspace document.
-#[derive(Debug, serde::Deserialize, Clone)]
+#[derive(Debug, serde::Deserialize, Clone, Default)]
+#[serde(default)]
 pub struct WorkspaceDocumentMetadata {
     pub id: String,
     pub url: String,
@@ -52,8 +110,7 @@ pub struct WorkspaceDocumentMetadata {
     #[serde(rename = "chunkSource")]
     pub chunk_source: String,
     pub description: String,
-    // #[serde(deserialize_with = "deserialize_utc_date")]
-    // pub published: DateTime<Utc>,
+    pub published: Option<String>,
     #[serde(rename = "wordCount")]
     pub word_count: u32,
 }
@@ -92,8 +149,92 @@ pub struct PostWorkspaceSlugChatResponse {
     pub chat_id: Option<u32>,
 }
 
+/// Structure to deserialize the response from the API.
+#[derive(Debug, serde::Deserialize)]
+pub struct GetWorkspaceChatsResponse {
+    pub history: Vec<WorkspaceChat>,
+}
+
+/// Represents a message in a workspace's chat history.
+#[derive(Debug, serde::Deserialize, Serialize, Clone)]
+pub struct WorkspaceChat {
+    pub role: ChatRole,
+    pub content: String,
+    #[serde(rename = "sentAt")]
+    pub sent_at: Option<i64>,
+    #[serde(default)]
+    pub sources: Vec<Source>,
+}
+
+/// The author of a chat message.
+#[derive(Debug, serde::Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    User,
+    Assistant,
+}
+
+/// Structure to deserialize a single server-sent event from the stream-chat API.
+#[derive(Debug, serde::Deserialize)]
+pub struct StreamChatFrame {
+    #[serde(alias = "uuid")]
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub response_type: String,
+    #[serde(rename = "textResponse")]
+    pub text_response: Option<String>,
+    pub sources: Option<Vec<Source>>,
+    #[serde(default)]
+    pub close: bool,
+    pub error: Option<serde_json::Value>,
+}
+
+/// A typed chunk of a streamed chat response.
+#[derive(Debug, Clone)]
+pub enum StreamChatChunk {
+    /// A fragment of the generated answer.
+    Text(String),
+    /// The sources used to generate the answer.
+    Sources(Vec<Source>),
+    /// The server has finished the response.
+    Close,
+    /// The server aborted the response.
+    Error(String),
+}
+
+impl From<StreamChatFrame> for Vec<StreamChatChunk> {
+    fn from(frame: StreamChatFrame) -> Self {
+        let mut chunks = Vec::new();
+
+        let error = match frame.error {
+            Some(serde_json::Value::String(message)) => Some(message),
+            Some(serde_json::Value::Bool(true)) => Some("unknown error".to_string()),
+            _ => None,
+        };
+        if let Some(message) = error
+            .or_else(|| (frame.response_type == "abort").then(|| "response aborted".to_string()))
+        {
+            chunks.push(StreamChatChunk::Error(message));
+            return chunks;
+        }
+
+        if let Some(text) = frame.text_response.filter(|t| !t.is_empty()) {
+            chunks.push(StreamChatChunk::Text(text));
+        }
+        if let Some(sources) = frame.sources.filter(|s| !s.is_empty()) {
+            chunks.push(StreamChatChunk::Sources(sources));
+        }
+        if frame.close {
+            chunks.push(StreamChatChunk::Close);
+        }
+
+        chunks
+    }
+}
+
 /// Represents a document source.
-#[derive(Debug, serde::Deserialize, Serialize)]
+#[derive(Debug, serde::Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
 pub struct Source {
     #[serde(rename = "chunkSource")]
     pub chunk_source: String,
@@ -1,21 +1,88 @@
+use eyre::WrapErr;
+
 use crate::anythingllm::client::AnythingLLMClient;
+use crate::app::{BackendArgs, ZoteroArgs};
+use crate::backend::local::LocalBackend;
+use crate::backend::{BackendType, RetrievalBackend};
 use crate::zotero::client::ZoteroClient;
+use crate::zotero::local::LocalZotero;
+use crate::zotero::source::{ZoteroSource, ZoteroSourceType};
 use crate::Config;
 
 pub mod admin;
+pub mod status;
 pub mod workspace;
 pub mod zotero;
 
-pub fn anythingllm_client() -> AnythingLLMClient {
-    let config = Config::from_file().unwrap();
-    AnythingLLMClient::new(
-        &config.anythingllm_ip,
-        &config.anythingllm_port,
+/// The AnythingLLM client configured in the configuration file.
+pub fn anythingllm_client() -> eyre::Result<AnythingLLMClient> {
+    let config = Config::from_file()?;
+    AnythingLLMClient::with_options(
+        &config.anythingllm_base_url(),
         &config.anythingllm_api_key,
+        &config.anythingllm_options(),
     )
+    .wrap_err("invalid AnythingLLM configuration")
+}
+
+/// The retrieval backend that workspace commands run against.
+///
+/// `args` override the backend and request limits set in the configuration file.
+pub fn backend(args: &BackendArgs) -> eyre::Result<Box<dyn RetrievalBackend>> {
+    let config = Config::from_file()?;
+    match args.backend.unwrap_or(config.backend) {
+        BackendType::AnythingLLM => {
+            let mut options = config.anythingllm_options();
+            if let Some(max_uploads) = args.max_uploads {
+                options.limits.uploads = max_uploads;
+            }
+            if let Some(max_chats) = args.max_chats {
+                options.limits.chats = max_chats;
+            }
+            if let Some(requests_per_second) = args.requests_per_second {
+                options.limits.requests_per_second = Some(requests_per_second);
+            }
+
+            let client = AnythingLLMClient::with_options(
+                &config.anythingllm_base_url(),
+                &config.anythingllm_api_key,
+                &options,
+            )
+            .wrap_err("invalid AnythingLLM configuration")?;
+            Ok(Box::new(client))
+        }
+        BackendType::Local => Ok(Box::new(LocalBackend::new(LocalBackend::default_root()))),
+    }
+}
+
+/// The Zotero client for the library selected by `args`, or else by the configuration file.
+pub fn zotero_client(args: &ZoteroArgs) -> eyre::Result<ZoteroClient> {
+    let config = Config::from_file()?;
+    let library = args.library.as_ref().unwrap_or(&config.zotero_library);
+    Ok(ZoteroClient::for_library(
+        &config.zotero_api_key,
+        &config.zotero_user_id,
+        library,
+    ))
 }
 
-pub fn zotero_client() -> ZoteroClient {
-    let config = Config::from_file().unwrap();
-    ZoteroClient::new(&config.zotero_api_key, &config.zotero_user_id)
+/// The source that read-only Zotero commands read the library from.
+///
+/// `args` override the source and library set in the configuration file. Commands that modify the
+/// library always use the web API.
+pub fn zotero_source(args: &ZoteroArgs) -> eyre::Result<Box<dyn ZoteroSource>> {
+    let config = Config::from_file()?;
+    match args.zotero_source.unwrap_or(config.zotero_source) {
+        ZoteroSourceType::Web => Ok(Box::new(zotero_client(args)?)),
+        ZoteroSourceType::Local => {
+            let library = args
+                .library
+                .clone()
+                .unwrap_or(config.zotero_library.clone());
+            Ok(Box::new(LocalZotero::new(
+                config.zotero_database_path(),
+                library,
+            )))
+        }
+    }
 }
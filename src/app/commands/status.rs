@@ -0,0 +1,140 @@
+//! Status command
+//!
+//! Reports the configuration of the AnythingLLM instance and whether ayda can use it.
+//!
+//! # Examples
+//! > ayda status
+//! > ayda status --json
+//!
+
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::anythingllm::client::AnythingLLMClient;
+use crate::anythingllm::error::LLMError;
+use crate::app::commands;
+
+/// The state of an AnythingLLM instance.
+#[derive(Debug, Default, Serialize)]
+pub struct StatusReport {
+    pub base_url: String,
+    pub reachable: bool,
+    pub api_key_valid: bool,
+    pub llm_provider: Option<String>,
+    pub llm_model: Option<String>,
+    pub embedding_engine: Option<String>,
+    pub embedding_model: Option<String>,
+    pub vector_db: Option<String>,
+    pub vector_count: Option<u64>,
+    pub workspace_count: Option<usize>,
+    pub error: Option<String>,
+}
+
+/// Print the status of the AnythingLLM instance, as a table or as JSON.
+///
+/// The report is printed in any case, but an error is returned when the instance is unreachable
+/// or rejects the API key, so that the command exits with a non-zero status.
+pub async fn status(json: bool) -> eyre::Result<()> {
+    let client = commands::anythingllm_client()?;
+    let report = status_report(&client).await;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+
+    if !report.reachable {
+        return Err(eyre::eyre!(
+            "AnythingLLM is not reachable at {}",
+            report.base_url
+        ));
+    }
+    if !report.api_key_valid {
+        return Err(eyre::eyre!("AnythingLLM API key is not valid"));
+    }
+
+    Ok(())
+}
+
+/// Collect the status of the instance the client is connected to.
+///
+/// Errors are recorded in the report rather than returned, so that a report can always be
+/// printed.
+pub async fn status_report(client: &AnythingLLMClient) -> StatusReport {
+    let mut report = StatusReport {
+        base_url: client.base_url.clone(),
+        ..Default::default()
+    };
+
+    match client.get_auth().await {
+        Ok(_) => {
+            report.reachable = true;
+            report.api_key_valid = true;
+        }
+        Err(LLMError::AuthError) => {
+            report.reachable = true;
+            report.error = Some(LLMError::AuthError.to_string());
+            return report;
+        }
+        Err(e) => {
+            report.error = Some(e.to_string());
+            return report;
+        }
+    }
+
+    let result = async {
+        let settings = client.get_system().await?;
+        report.llm_model = settings.llm_model();
+        report.llm_provider = settings.llm_provider;
+        report.embedding_engine = settings.embedding_engine;
+        report.embedding_model = settings.embedding_model;
+        report.vector_db = settings.vector_db;
+        report.vector_count = Some(client.get_system_vector_count().await?);
+        report.workspace_count = Some(client.get_workspaces().await?.len());
+        Ok::<_, LLMError>(())
+    }
+    .await;
+
+    if let Err(e) = result {
+        report.error = Some(e.to_string());
+    }
+
+    report
+}
+
+// helper functions //////////////////////////////////////////////////////////////////////////////
+
+fn print_report(report: &StatusReport) {
+    let yes_no = |b: bool| if b { "yes".green() } else { "no".red() };
+    let value = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+    let count = |v: Option<_>| v.map_or("-".to_string(), |n: u64| n.to_string());
+
+    println!();
+    println!("{:<18} {}", "URL", report.base_url);
+    println!("{:<18} {}", "Reachable", yes_no(report.reachable));
+    println!("{:<18} {}", "API key valid", yes_no(report.api_key_valid));
+    println!("{:<18} {}", "LLM provider", value(&report.llm_provider));
+    println!("{:<18} {}", "LLM model", value(&report.llm_model));
+    println!(
+        "{:<18} {}",
+        "Embedding engine",
+        value(&report.embedding_engine)
+    );
+    println!(
+        "{:<18} {}",
+        "Embedding model",
+        value(&report.embedding_model)
+    );
+    println!("{:<18} {}", "Vector DB", value(&report.vector_db));
+    println!("{:<18} {}", "Vectors", count(report.vector_count));
+    println!(
+        "{:<18} {}",
+        "Workspaces",
+        count(report.workspace_count.map(|n| n as u64))
+    );
+
+    if let Some(error) = &report.error {
+        println!("\n{}", error.red());
+    }
+}
@@ -1,24 +1,50 @@
+use std::io::Write;
+
 use crate::anythingllm::workspace::endpoint::ChatMode;
-use crate::app::commands;
+use crate::anythingllm::workspace::{Source, StreamChatChunk};
+use crate::backend::RetrievalBackend;
 use colored::*;
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::Input;
-use hyphenation::{Language, Load, Standard};
-use textwrap::{fill, Options, WordSplitter};
+use futures::StreamExt;
+
+const LINE_WIDTH: usize = 120;
 
 /// Chat to the workspace.
 ///
 /// A workspace has a chat mode, which can be set to either `Chat` (will not use LLM unless there
 /// are relevant sources from vectorDB & does not recall chat history) or `Query` (uses LLM general
 /// knowledge w/custom embeddings to produce output, uses rolling chat history).
-pub async fn chat(workspace_name: String, chat_mode: ChatMode) -> eyre::Result<()> {
-    let dictionary = Standard::from_embedded(Language::EnglishUS).unwrap();
-    let options = Options::new(120).word_splitter(WordSplitter::Hyphenation(dictionary));
-    let client = commands::anythingllm_client();
-    let workspace = client.get_workspace_by_name(&workspace_name).await;
+///
+/// Responses are streamed and printed as they arrive, followed by the sources used.
+///
+/// If `thread_name` is given, the conversation takes place in the workspace thread with that name,
+/// which is created if it doesn't exist.
+pub async fn chat(
+    backend: &dyn RetrievalBackend,
+    workspace_name: String,
+    chat_mode: ChatMode,
+    thread_name: Option<String>,
+) -> eyre::Result<()> {
+    let workspace = backend.workspace_by_name(&workspace_name).await;
     if workspace.is_err() {
         return Err(eyre::eyre!("Workspace not found"));
     }
+    let slug = workspace.as_ref().unwrap().slug.clone();
+
+    let thread = match thread_name {
+        Some(name) => {
+            let (thread, created) = backend.thread(&slug, &name).await?;
+            let message = match created {
+                true => format!("Created thread '{}'", thread.name),
+                false => format!("Resuming thread '{}'", thread.name),
+            };
+            println!("{}", message.dimmed());
+            Some(thread)
+        }
+        None => None,
+    };
+    let thread_slug = thread.as_ref().map(|t| t.slug.as_str());
 
     let mut chat_mode = chat_mode;
 
@@ -34,17 +60,13 @@ pub async fn chat(workspace_name: String, chat_mode: ChatMode) -> eyre::Result<(
         match query.as_str() {
             "/query" => {
                 chat_mode = ChatMode::Query;
-                client
-                    .post_workspace_slug_chat(&workspace.as_ref().unwrap().slug, &query, &chat_mode)
-                    .await?;
+                backend.chat(&slug, thread_slug, &query, &chat_mode).await?;
                 println!("{}", "Query mode set".dimmed());
                 continue;
             }
             "/chat" => {
                 chat_mode = ChatMode::Chat;
-                client
-                    .post_workspace_slug_chat(&workspace.as_ref().unwrap().slug, &query, &chat_mode)
-                    .await?;
+                backend.chat(&slug, thread_slug, &query, &chat_mode).await?;
                 println!("{}", "Chat mode set".dimmed());
                 continue;
             }
@@ -52,16 +74,30 @@ pub async fn chat(workspace_name: String, chat_mode: ChatMode) -> eyre::Result<(
             _ => {}
         }
 
-        let response = client
-            .post_workspace_slug_chat(&workspace.as_ref().unwrap().slug, &query, &chat_mode)
+        let mut chunks = backend
+            .stream_chat(&slug, thread_slug, &query, &chat_mode)
             .await?;
 
-        let wrapped = fill(&response.text_response, &options);
-        println!("{}", wrapped);
+        let mut printer = StreamPrinter::new(LINE_WIDTH);
+        let mut sources = Vec::<Source>::new();
 
-        if !response.sources.is_empty() {
+        while let Some(chunk) = chunks.next().await {
+            match chunk? {
+                StreamChatChunk::Text(text) => printer.push(&text),
+                StreamChatChunk::Sources(s) => sources.extend(s),
+                StreamChatChunk::Close => break,
+                StreamChatChunk::Error(e) => {
+                    printer.finish();
+                    println!("{}", e.red());
+                    break;
+                }
+            }
+        }
+        printer.finish();
+
+        if !sources.is_empty() {
             println!("\n{}", "Sources:".bold());
-            for source in response.sources {
+            for source in sources {
                 println!(" - {}", source.title);
             }
         }
@@ -69,3 +105,63 @@ pub async fn chat(workspace_name: String, chat_mode: ChatMode) -> eyre::Result<(
 
     Ok(())
 }
+
+// Prints streamed text word by word, wrapping lines at a fixed width
+struct StreamPrinter {
+    width: usize,
+    column: usize,
+    word: String,
+}
+
+impl StreamPrinter {
+    fn new(width: usize) -> Self {
+        Self {
+            width,
+            column: 0,
+            word: String::new(),
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        for c in text.chars() {
+            match c {
+                '\n' => {
+                    self.flush_word();
+                    println!();
+                    self.column = 0;
+                }
+                c if c.is_whitespace() => {
+                    self.flush_word();
+                    if self.column > 0 && self.column < self.width {
+                        print!(" ");
+                        self.column += 1;
+                    }
+                }
+                c => self.word.push(c),
+            }
+        }
+        std::io::stdout().flush().unwrap();
+    }
+
+    fn finish(&mut self) {
+        self.flush_word();
+        if self.column > 0 {
+            println!();
+            self.column = 0;
+        }
+    }
+
+    fn flush_word(&mut self) {
+        if self.word.is_empty() {
+            return;
+        }
+        let len = self.word.chars().count();
+        if self.column > 0 && self.column + len > self.width {
+            println!();
+            self.column = 0;
+        }
+        print!("{}", self.word);
+        self.column += len;
+        self.word.clear();
+    }
+}
@@ -0,0 +1,96 @@
+use colored::*;
+use dialoguer::Editor;
+use serde_json::{from_str, to_string_pretty};
+
+use crate::anythingllm::workspace::{Workspace, WorkspaceSettings};
+use crate::app;
+use crate::app::commands;
+
+/// Show or change the settings of a workspace.
+///
+/// With no settings given, the current settings are displayed. Otherwise the given settings are
+/// applied. If `edit` is true, the current settings are opened in an editor and the edited
+/// settings are applied.
+pub async fn configure(
+    workspace_name: String,
+    settings: WorkspaceSettings,
+    edit: bool,
+) -> eyre::Result<()> {
+    let client = commands::anythingllm_client()?;
+    let workspace = client.get_workspace_by_name(&workspace_name).await?;
+
+    let settings = match edit {
+        true => match edit_settings(&workspace)? {
+            Some(settings) => settings,
+            None => {
+                println!("Abort!");
+                return Ok(());
+            }
+        },
+        false => settings,
+    };
+
+    if settings.is_empty() {
+        display_settings(&workspace);
+        return Ok(());
+    }
+
+    let workspace = client
+        .update_workspace_settings(&workspace.slug, &settings)
+        .await?;
+
+    println!(
+        "{}",
+        format!("Updated settings for workspace '{}'", workspace.name).green()
+    );
+    display_settings(&workspace);
+
+    Ok(())
+}
+
+// Open the workspace's current settings in an editor and return the edited settings
+fn edit_settings(workspace: &Workspace) -> eyre::Result<Option<WorkspaceSettings>> {
+    let settings_str = to_string_pretty(&WorkspaceSettings::from(workspace))?;
+
+    match Editor::new().extension(".json").edit(&settings_str)? {
+        Some(rv) => Ok(Some(from_str::<WorkspaceSettings>(&rv)?)),
+        None => Ok(None),
+    }
+}
+
+fn display_settings(workspace: &Workspace) {
+    let column_titles = vec!["SETTING", "VALUE"];
+    app::display_table(column_titles, data_from_workspace(workspace));
+}
+
+fn data_from_workspace(workspace: &Workspace) -> Vec<Vec<String>> {
+    let default = || "default".to_string();
+
+    vec![
+        vec![
+            "similarity threshold".to_string(),
+            workspace.similarity_threshold.to_string(),
+        ],
+        vec![
+            "temperature".to_string(),
+            workspace
+                .temperature
+                .map_or_else(default, |t| t.to_string()),
+        ],
+        vec![
+            "history length".to_string(),
+            workspace
+                .history_length
+                .map_or_else(default, |h| h.to_string()),
+        ],
+        vec![
+            "top n".to_string(),
+            workspace.top_n.map_or_else(default, |n| n.to_string()),
+        ],
+        vec![
+            "system prompt".to_string(),
+            workspace.system_prompt.clone().unwrap_or_else(default),
+        ],
+        vec!["chat mode".to_string(), workspace.chat_mode.to_string()],
+    ]
+}
@@ -2,16 +2,14 @@ use colored::*;
 use eyre::Context;
 
 use crate::anythingllm::error::LLMError;
-use crate::app::commands;
+use crate::backend::RetrievalBackend;
 
 /// Create a new workspace.
 /// Method will fail if a workspace with the same name already exists.
-pub async fn create(workspace_name: String) -> eyre::Result<()> {
-    let client = commands::anythingllm_client();
-
+pub async fn create(backend: &dyn RetrievalBackend, workspace_name: String) -> eyre::Result<()> {
     // fail if workspace(s) already exists
 
-    match client.get_workspace_by_name(&workspace_name).await {
+    match backend.workspace_by_name(&workspace_name).await {
         Ok(_) => {
             println!(
                 "{}",
@@ -35,7 +33,7 @@ pub async fn create(workspace_name: String) -> eyre::Result<()> {
 
     // create the workspace
 
-    client
+    backend
         .create_workspace(&workspace_name)
         .await
         .wrap_err("couldn't create new workspace")?;
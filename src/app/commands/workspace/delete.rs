@@ -1,16 +1,15 @@
 use colored::*;
 use dialoguer::Confirm;
 
-use crate::app::commands;
+use crate::backend::RetrievalBackend;
 
 /// Delete a workspace.
-pub async fn delete(workspace_name: String) -> eyre::Result<()> {
-    let client = commands::anythingllm_client();
-    let workspace = client.get_workspace_by_name(&workspace_name).await?;
+pub async fn delete(backend: &dyn RetrievalBackend, workspace_name: String) -> eyre::Result<()> {
+    let workspace = backend.workspace_by_name(&workspace_name).await?;
 
     // fail if unique workspace doesn't exist
 
-    match client.get_workspace_by_name(&workspace_name).await {
+    match backend.workspace_by_name(&workspace_name).await {
         Ok(_) => {}
         _ => {
             println!(
@@ -37,7 +36,7 @@ pub async fn delete(workspace_name: String) -> eyre::Result<()> {
 
     // delete the workspace
 
-    let result = client.delete_workspace_slug(&workspace.slug).await;
+    let result = backend.delete_workspace(&workspace.slug).await;
 
     match result {
         Ok(_) => {
@@ -51,8 +50,7 @@ pub async fn delete(workspace_name: String) -> eyre::Result<()> {
 }
 
 /// Delete all workspaces.
-
-pub async fn delete_all() -> eyre::Result<()> {
+pub async fn delete_all(backend: &dyn RetrievalBackend) -> eyre::Result<()> {
     let confirm = Confirm::new()
         .with_prompt("Are you sure you want to delete all workspace?")
         .interact()?;
@@ -62,10 +60,9 @@ pub async fn delete_all() -> eyre::Result<()> {
         return Ok(());
     }
 
-    let client = commands::anythingllm_client();
-    let workspaces = client.get_workspaces().await?;
+    let workspaces = backend.workspaces().await?;
     for ws in workspaces {
-        let result = client.delete_workspace_slug(&ws.slug).await;
+        let result = backend.delete_workspace(&ws.slug).await;
         match result {
             Ok(_) => {
                 println!("Deleted workspace '{}'", ws.name);
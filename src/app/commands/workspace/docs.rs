@@ -0,0 +1,96 @@
+use eyre::Context;
+
+use crate::anythingllm::workspace::WorkspaceDocument;
+use crate::app;
+use crate::app::DocSortKey;
+use crate::backend::RetrievalBackend;
+
+/// List the documents in a workspace.
+///
+/// Documents can be filtered by title or author and by whether they are pinned, and sorted by
+/// title, author, word count or date added.
+pub async fn docs(
+    backend: &dyn RetrievalBackend,
+    workspace_name: String,
+    sort: DocSortKey,
+    reverse: bool,
+    filter: Option<String>,
+    pinned: bool,
+) -> eyre::Result<()> {
+    let workspace = backend.workspace_by_name(&workspace_name).await?;
+    let documents = backend
+        .workspace_documents(&workspace.slug)
+        .await
+        .wrap_err("couldn't get workspace documents")?;
+
+    let documents = filter_documents(documents, filter.as_deref(), pinned);
+    if documents.is_empty() {
+        println!("No documents found");
+        return Ok(());
+    }
+
+    let documents = sort_documents(documents, &sort, reverse);
+    let document_count = documents.len();
+
+    let column_titles = vec!["TITLE", "AUTHOR", "WORDS", "PINNED", "CREATED"];
+    let data = data_from_documents(documents);
+    app::display_table(column_titles, data);
+
+    println!("\n{} document(s)", document_count);
+
+    Ok(())
+}
+
+fn filter_documents(
+    documents: Vec<WorkspaceDocument>,
+    filter: Option<&str>,
+    pinned: bool,
+) -> Vec<WorkspaceDocument> {
+    let filter = filter.map(str::to_lowercase);
+
+    documents
+        .into_iter()
+        .filter(|d| !pinned || d.pinned)
+        .filter(|d| match &filter {
+            Some(f) => {
+                d.metadata.title.to_lowercase().contains(f)
+                    || d.metadata.doc_author.to_lowercase().contains(f)
+            }
+            None => true,
+        })
+        .collect()
+}
+
+fn sort_documents(
+    mut documents: Vec<WorkspaceDocument>,
+    sort: &DocSortKey,
+    reverse: bool,
+) -> Vec<WorkspaceDocument> {
+    match sort {
+        DocSortKey::Title => documents.sort_by_key(|d| d.metadata.title.to_lowercase()),
+        DocSortKey::Author => documents.sort_by_key(|d| d.metadata.doc_author.to_lowercase()),
+        DocSortKey::Words => documents.sort_by_key(|d| d.metadata.word_count),
+        DocSortKey::Created => documents.sort_by_key(|d| d.created_at),
+    }
+
+    if reverse {
+        documents.reverse();
+    }
+
+    documents
+}
+
+fn data_from_documents(documents: Vec<WorkspaceDocument>) -> Vec<Vec<String>> {
+    documents
+        .iter()
+        .map(|d| {
+            vec![
+                d.metadata.title.clone(),
+                d.metadata.doc_author.clone(),
+                d.metadata.word_count.to_string(),
+                if d.pinned { "yes" } else { "" }.to_string(),
+                d.created_at.format("%Y-%m-%d").to_string(),
+            ]
+        })
+        .collect()
+}
@@ -0,0 +1,129 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use colored::*;
+use serde::Serialize;
+
+use crate::anythingllm::workspace::{ChatRole, Source, WorkspaceChat};
+use crate::app::commands;
+use crate::app::HistoryFormat;
+
+/// A prompt and the answer to it.
+#[derive(Debug, Serialize)]
+pub struct Exchange {
+    pub sent_at: Option<DateTime<Utc>>,
+    pub prompt: String,
+    pub response: String,
+    pub sources: Vec<Source>,
+}
+
+/// Show the chat history of a workspace, or export it.
+///
+/// If `format` is given the history is exported in that format to `output`, or to stdout if no
+/// output file is given. Each answer keeps the list of sources used to produce it.
+pub async fn history(
+    workspace_name: String,
+    format: Option<HistoryFormat>,
+    output: Option<PathBuf>,
+) -> eyre::Result<()> {
+    let client = commands::anythingllm_client()?;
+    let workspace = client.get_workspace_by_name(&workspace_name).await?;
+    let chats = client.get_workspace_chats(&workspace.slug).await?;
+    let exchanges = exchanges_from_chats(chats);
+
+    let Some(format) = format else {
+        display_exchanges(&exchanges);
+        return Ok(());
+    };
+
+    let rendered = match format {
+        HistoryFormat::Markdown => render_markdown(&workspace.name, &exchanges),
+        HistoryFormat::Json => serde_json::to_string_pretty(&exchanges)?,
+    };
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, rendered)?;
+            println!(
+                "Exported {} exchange(s) to {}",
+                exchanges.len(),
+                path.display()
+            );
+        }
+        None => println!("{}", rendered),
+    }
+
+    Ok(())
+}
+
+// Pair each user prompt with the assistant response that follows it
+fn exchanges_from_chats(chats: Vec<WorkspaceChat>) -> Vec<Exchange> {
+    let mut exchanges = Vec::<Exchange>::new();
+
+    for chat in chats {
+        match chat.role {
+            ChatRole::User => exchanges.push(Exchange {
+                sent_at: chat.sent_at.and_then(|t| DateTime::from_timestamp(t, 0)),
+                prompt: chat.content,
+                response: String::new(),
+                sources: Vec::new(),
+            }),
+            ChatRole::Assistant => match exchanges.last_mut() {
+                Some(exchange) if exchange.response.is_empty() => {
+                    exchange.response = chat.content;
+                    exchange.sources = chat.sources;
+                }
+                _ => exchanges.push(Exchange {
+                    sent_at: chat.sent_at.and_then(|t| DateTime::from_timestamp(t, 0)),
+                    prompt: String::new(),
+                    response: chat.content,
+                    sources: chat.sources,
+                }),
+            },
+        }
+    }
+
+    exchanges
+}
+
+fn display_exchanges(exchanges: &[Exchange]) {
+    if exchanges.is_empty() {
+        println!("No chat history found");
+        return;
+    }
+
+    for exchange in exchanges {
+        println!();
+        if let Some(sent_at) = exchange.sent_at {
+            println!("{}", sent_at.format("%Y-%m-%d %H:%M").to_string().dimmed());
+        }
+        println!("{} {}", "Prompt:".bold(), exchange.prompt);
+        println!("{}", exchange.response);
+        for source in &exchange.sources {
+            println!(" - {}", source.title.dimmed());
+        }
+    }
+}
+
+fn render_markdown(workspace_name: &str, exchanges: &[Exchange]) -> String {
+    let mut md = format!("# Chat history: {}\n", workspace_name);
+
+    for exchange in exchanges {
+        md.push('\n');
+        match exchange.sent_at {
+            Some(sent_at) => md.push_str(&format!("## {}\n\n", sent_at.format("%Y-%m-%d %H:%M"))),
+            None => md.push_str("## Undated\n\n"),
+        }
+        md.push_str(&format!("**Prompt:** {}\n\n", exchange.prompt));
+        md.push_str(&format!("{}\n", exchange.response));
+
+        if !exchange.sources.is_empty() {
+            md.push_str("\n**Sources:**\n\n");
+            for source in &exchange.sources {
+                md.push_str(&format!("- {} (score {:.2})\n", source.title, source.score));
+            }
+        }
+    }
+
+    md
+}
@@ -1,7 +1,7 @@
 use colored::*;
-use std::io;
+use std::collections::HashSet;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio::fs;
 
@@ -11,11 +11,14 @@ use dialoguer::Confirm;
 use indicatif::{ProgressBar, ProgressStyle};
 
 use crate::zotero::collection::models::Collection;
-use tracing::{event, info, span, Instrument, Level};
+use tracing::{event, span, Instrument, Level};
 
-use crate::anythingllm::workspace::models::Workspace;
-use crate::app::commands;
+use crate::anythingllm::error::LLMError;
+use crate::anythingllm::workspace::models::{Workspace, WorkspaceDocument};
+use crate::backend::RetrievalBackend;
+use crate::zotero::cache::LibraryCache;
 use crate::zotero::item::models::Item;
+use crate::zotero::source::ZoteroSource;
 use crate::Config;
 use eyre::eyre;
 use std::sync::Arc;
@@ -23,41 +26,74 @@ use tokio::sync::Mutex;
 
 /// Import items from a Zotero collection to a workspace.
 ///
-pub async fn import_zotero(collection_name: String) -> eyre::Result<()> {
+/// Documents that fail to embed are retried up to `retries` times. If `recursive` is set, the
+/// items in the collection's subcollections are imported too.
+pub async fn import_zotero(
+    backend: &dyn RetrievalBackend,
+    zotero: &dyn ZoteroSource,
+    collection_name: String,
+    retries: u32,
+    recursive: bool,
+) -> eyre::Result<()> {
     println!("Importing documents from '{}'", collection_name);
 
-    let collection = match get_collection(&collection_name).await {
+    let library = read_library(zotero).await?;
+
+    let collection = match get_collection(&library, &collection_name) {
         Ok(collection) => collection,
         Err(e) => {
             return Err(e);
         }
     };
 
-    let pdfs = match get_pdfs_from_collection(&collection).await {
+    let (pdfs, webpages) = match get_documents_from_collection(&library, &collection, recursive) {
         Ok(docs) => docs,
         Err(e) => {
             return Err(e);
         }
     };
 
-    let pdf_file_paths = file_paths(pdfs);
+    // only PDFs are read from the library's storage directory
+    let pdf_file_paths = match pdfs.is_empty() {
+        true => Vec::new(),
+        false => file_paths(
+            &Config::from_file()?.zotero_storage_path(zotero.library()),
+            pdfs,
+        ),
+    };
 
-    let workspace = match create_or_get_workspace("zotero", &collection_name).await {
+    let workspace = match create_or_get_workspace(backend, "zotero", &collection_name).await {
         Ok(workspace) => workspace,
         Err(e) => {
             return Err(e);
         }
     };
 
-    let uploaded_pdf_ids = match upload_pdfs(pdf_file_paths).await {
-        Ok(uploaded_pdf_ids) => uploaded_pdf_ids,
-        Err(e) => {
-            return Err(e);
-        }
-    };
+    let mut uploaded_doc_ids = Vec::new();
 
-    if embed_pdfs(&workspace, uploaded_pdf_ids).await.is_err() {
-        return Err(eyre!("Error embedding PDFs"));
+    if !pdf_file_paths.is_empty() {
+        match upload_pdfs(backend, pdf_file_paths).await {
+            Ok(uploaded_pdf_ids) => uploaded_doc_ids.extend(uploaded_pdf_ids),
+            Err(e) => {
+                return Err(e);
+            }
+        };
+    }
+
+    if !webpages.is_empty() {
+        match upload_links(backend, webpages).await {
+            Ok(uploaded_link_ids) => uploaded_doc_ids.extend(uploaded_link_ids),
+            Err(e) => {
+                return Err(e);
+            }
+        };
+    }
+
+    if embed_documents(backend, &workspace, uploaded_doc_ids, retries)
+        .await
+        .is_err()
+    {
+        return Err(eyre!("Error embedding documents"));
     }
 
     println!("done");
@@ -67,7 +103,12 @@ pub async fn import_zotero(collection_name: String) -> eyre::Result<()> {
 
 /// Import items from a folder into a workspace.
 ///
-pub async fn import_folder(folder_path: PathBuf) -> eyre::Result<()> {
+/// Documents that fail to embed are retried up to `retries` times.
+pub async fn import_folder(
+    backend: &dyn RetrievalBackend,
+    folder_path: PathBuf,
+    retries: u32,
+) -> eyre::Result<()> {
     println!("Importing documents from {}", folder_path.display());
 
     let pdfs = match get_pdf_filepaths_from_directory(&folder_path).await {
@@ -78,22 +119,25 @@ pub async fn import_folder(folder_path: PathBuf) -> eyre::Result<()> {
     };
 
     let folder_name = folder_path.file_name().unwrap().to_string_lossy();
-    let workspace = match create_or_get_workspace("folder", &folder_name).await {
+    let workspace = match create_or_get_workspace(backend, "folder", &folder_name).await {
         Ok(workspace) => workspace,
         Err(e) => {
             return Err(e);
         }
     };
 
-    let uploaded_pdf_ids = match upload_pdfs(pdfs).await {
+    let uploaded_pdf_ids = match upload_pdfs(backend, pdfs).await {
         Ok(uploaded_pdf_ids) => uploaded_pdf_ids,
         Err(e) => {
             return Err(e);
         }
     };
 
-    if embed_pdfs(&workspace, uploaded_pdf_ids).await.is_err() {
-        return Err(eyre!("Error embedding PDFs"));
+    if embed_documents(backend, &workspace, uploaded_pdf_ids, retries)
+        .await
+        .is_err()
+    {
+        return Err(eyre!("Error embedding documents"));
     }
 
     println!("done");
@@ -102,7 +146,6 @@ pub async fn import_folder(folder_path: PathBuf) -> eyre::Result<()> {
 }
 
 /// Import an item.
-
 pub async fn import_item() -> eyre::Result<()> {
     Ok(())
 }
@@ -156,30 +199,42 @@ pub enum UpdateParameter {
     Deletes,
 }
 
-pub async fn get_collection(collection_name: &str) -> eyre::Result<Collection> {
+/// Read the Zotero library's items and collections, once for the whole command.
+pub async fn read_library(zotero: &dyn ZoteroSource) -> eyre::Result<LibraryCache> {
+    let sp = Spinner::new("Reading Zotero library...".to_string());
+    match zotero.read_library().await {
+        Ok(library) => {
+            sp.finish_ok("Library OK".to_string());
+            Ok(library)
+        }
+        Err(e) => {
+            sp.finish_error(format!("Unable to read library: {}", e));
+            Err(eyre::Report::new(e).wrap_err("Error reading Zotero library"))
+        }
+    }
+}
+
+pub fn get_collection(library: &LibraryCache, collection_name: &str) -> eyre::Result<Collection> {
     let sp = Spinner::new("Checking collection...".to_string());
-    let zotero = commands::zotero_client();
-    let collection = zotero.collection_from_name(&collection_name).await;
 
-    match collection {
+    match library.collection_from_name(collection_name) {
         Ok(collection) => {
             sp.finish_ok("Collection OK".to_string());
             Ok(collection)
         }
-        Err(_) => {
-            sp.finish_error("Collection not found. Run 'zotero list-collections'".to_string());
-            Err(eyre!("Error checking collection"))
+        Err(e) => {
+            sp.finish_error(format!("{}. Run 'zotero list-collections'", e));
+            Err(eyre::Report::new(e).wrap_err("Error checking collection"))
         }
     }
 }
 
-pub async fn get_pdfs_from_collection(collection: &Collection) -> eyre::Result<Vec<Item>> {
+pub fn get_pdfs_from_collection(
+    library: &LibraryCache,
+    collection: &Collection,
+) -> eyre::Result<Vec<Item>> {
     let sp = Spinner::new("Checking PDFs...".to_string());
-    let zotero = commands::zotero_client();
-    let items: Vec<Item> = zotero
-        .get_collections_collection_key_items_batched(collection.clone().key)
-        .collect()
-        .await;
+    let items: Vec<Item> = library.collection_items(&collection.key);
     let pdfs: Vec<Item> = items.into_iter().filter(|item| item.is_pdf()).collect();
 
     if !pdfs.is_empty() {
@@ -191,6 +246,35 @@ pub async fn get_pdfs_from_collection(collection: &Collection) -> eyre::Result<V
     }
 }
 
+/// Get the PDF and web page items in a collection, and in its subcollections if `recursive` is set.
+pub fn get_documents_from_collection(
+    library: &LibraryCache,
+    collection: &Collection,
+    recursive: bool,
+) -> eyre::Result<(Vec<Item>, Vec<Item>)> {
+    let sp = Spinner::new("Checking documents...".to_string());
+    let items: Vec<Item> = match recursive {
+        true => library.collection_items_recursive(&collection.key),
+        false => library.collection_items(&collection.key),
+    };
+    let (pdfs, webpages): (Vec<Item>, Vec<Item>) = items
+        .into_iter()
+        .filter(|item| item.is_pdf() || item.is_webpage())
+        .partition(|item| item.is_pdf());
+
+    if pdfs.is_empty() && webpages.is_empty() {
+        sp.finish_error("No PDFs or web pages found in collection".to_string());
+        Err(eyre!("Error getting documents"))
+    } else {
+        sp.finish_ok(format!(
+            "{} PDFS and {} web pages found",
+            pdfs.len(),
+            webpages.len()
+        ));
+        Ok((pdfs, webpages))
+    }
+}
+
 async fn get_pdf_filepaths_from_directory(folder_path: &PathBuf) -> eyre::Result<Vec<PathBuf>> {
     let sp = Spinner::new("Getting PDFs...".to_string());
     match folder_path.exists() {
@@ -218,30 +302,30 @@ async fn get_pdf_filepaths_from_directory(folder_path: &PathBuf) -> eyre::Result
     }
 }
 
-async fn create_or_get_workspace(prefix: &str, collection_name: &str) -> eyre::Result<Workspace> {
+async fn create_or_get_workspace(
+    backend: &dyn RetrievalBackend,
+    prefix: &str,
+    collection_name: &str,
+) -> eyre::Result<Workspace> {
     let sp = Spinner::new("Checking workspace...".to_string());
-    let anythingllm = commands::anythingllm_client();
     let workspace_name = format!("{}-{}", prefix, collection_name);
 
-    match anythingllm.get_workspace_by_name(&workspace_name).await {
-        Ok(workspace) => {
-            sp.finish_error("Workspace exists".to_string());
-            let confirmation = Confirm::new()
-                .with_prompt("  Do you want to continue?")
-                .interact()
-                .expect("Error getting confirmation");
-
-            if !confirmation {
-                sp.finish_error("Cancelled".to_string());
-                return Err(eyre!("Error creating workspace"));
-            }
+    if let Ok(workspace) = backend.workspace_by_name(&workspace_name).await {
+        sp.finish_error("Workspace exists".to_string());
+        let confirmation = Confirm::new()
+            .with_prompt("  Do you want to continue?")
+            .interact()
+            .expect("Error getting confirmation");
 
-            let _ = anythingllm.delete_workspace_slug(&workspace.slug).await;
+        if !confirmation {
+            sp.finish_error("Cancelled".to_string());
+            return Err(eyre!("Error creating workspace"));
         }
-        Err(_) => {}
+
+        let _ = backend.delete_workspace(&workspace.slug).await;
     }
 
-    match anythingllm.create_workspace(&workspace_name).await {
+    match backend.create_workspace(&workspace_name).await {
         Ok(workspace) => {
             sp.finish_ok(format!("Created workspace {}", workspace.name));
             Ok(workspace)
@@ -253,7 +337,10 @@ async fn create_or_get_workspace(prefix: &str, collection_name: &str) -> eyre::R
     }
 }
 
-async fn upload_pdfs(file_paths: Vec<PathBuf>) -> eyre::Result<Vec<String>> {
+async fn upload_pdfs(
+    backend: &dyn RetrievalBackend,
+    file_paths: Vec<PathBuf>,
+) -> eyre::Result<Vec<String>> {
     let mut failures = Vec::<PathBuf>::new(); // Declare failures as mutable
 
     let doc_count = file_paths.len();
@@ -264,13 +351,11 @@ async fn upload_pdfs(file_paths: Vec<PathBuf>) -> eyre::Result<Vec<String>> {
     bar.set_style(bar_style.progress_chars("##-"));
 
     // share one copy between multiple readers using reference counting
-    let anythingllm = Arc::new(commands::anythingllm_client());
     let failed_docs = Arc::new(Mutex::new(Vec::<PathBuf>::new()));
 
     let docs: Vec<_> = stream::iter(file_paths)
         .map(|document_filepath| {
             let span = span!(Level::INFO, "process PDF");
-            let anythingllm = anythingllm.clone();
             let failed_docs = failed_docs.clone();
             let bar = bar.clone();
 
@@ -278,7 +363,7 @@ async fn upload_pdfs(file_paths: Vec<PathBuf>) -> eyre::Result<Vec<String>> {
                 event!(Level::INFO, "Uploading");
 
                 // Upload PDFs
-                match anythingllm.post_document_upload(&document_filepath).await {
+                match backend.upload_document(&document_filepath).await {
                     Ok(doc) => {
                         event!(Level::INFO, "upload success");
                         bar.inc(1);
@@ -298,7 +383,7 @@ async fn upload_pdfs(file_paths: Vec<PathBuf>) -> eyre::Result<Vec<String>> {
             }
             .instrument(span)
         })
-        .buffered(100)
+        .buffered(backend.limits().uploads.max(1))
         .filter_map(|f| async { f })
         .collect()
         .await;
@@ -332,31 +417,156 @@ async fn upload_pdfs(file_paths: Vec<PathBuf>) -> eyre::Result<Vec<String>> {
     Ok(docs)
 }
 
-async fn embed_pdfs(workspace: &Workspace, pdfs: Vec<String>) -> eyre::Result<()> {
-    let sp = Spinner::new("Embedding PDFs in workspace...".to_string());
-    let anythingllm = commands::anythingllm_client();
+// Upload web page items by their URL, reporting the outcome for each item
+async fn upload_links(
+    backend: &dyn RetrievalBackend,
+    webpages: Vec<Item>,
+) -> eyre::Result<Vec<String>> {
+    let doc_count = webpages.len();
+    let bar = ProgressBar::new(doc_count as u64);
+    let bar_style = ProgressStyle::default_bar()
+        .template("{bar:100.cyan/blue} {pos:>7}/{len:7} {msg} {eta}")
+        .unwrap();
+    bar.set_style(bar_style.progress_chars("##-"));
+
+    let results: Vec<_> = stream::iter(webpages)
+        .map(|webpage| {
+            let span = span!(Level::INFO, "process web page");
+            let bar = bar.clone();
 
-    match anythingllm
-        .update_embeddings(&workspace.slug, pdfs, UpdateParameter::Adds)
-        .await
-    {
-        Ok(_) => {
-            sp.finish_ok("Embedding OK".to_string());
-            Ok(())
+            async move {
+                let url = webpage.url.clone().unwrap_or_default();
+                event!(Level::INFO, "Uploading link {}", url);
+                let result = backend.upload_link(&url).await;
+                bar.inc(1);
+                (webpage, result)
+            }
+            .instrument(span)
+        })
+        .buffered(backend.limits().uploads.max(1))
+        .collect()
+        .await;
+
+    bar.finish();
+
+    let mut docs = Vec::new();
+    for (webpage, result) in results {
+        match result.map(|doc| doc.location) {
+            Ok(Some(location)) => {
+                println!("{}", format!("  ✓ {}", webpage.title).green());
+                docs.push(location);
+            }
+            Ok(None) => println!(
+                "{}",
+                format!("  ✗ {}: no document location returned", webpage.title).red()
+            ),
+            Err(e) => println!(
+                "{}",
+                format!(
+                    "  ✗ {} ({}): {}",
+                    webpage.title,
+                    webpage.url.unwrap_or_default(),
+                    e
+                )
+                .red()
+            ),
         }
+    }
+
+    let message = format!("  {}/{} web page(s) uploaded.", docs.len(), doc_count);
+    match docs.len() == doc_count {
+        true => println!("{}", message.green()),
+        false => println!("{}", message.red()),
+    }
+
+    Ok(docs)
+}
+
+async fn embed_documents(
+    backend: &dyn RetrievalBackend,
+    workspace: &Workspace,
+    documents: Vec<String>,
+    retries: u32,
+) -> eyre::Result<()> {
+    let sp = Spinner::new("Embedding documents in workspace...".to_string());
+    let doc_count = documents.len();
+
+    let missing = match embed_and_verify(backend, &workspace.slug, documents, retries).await {
+        Ok(missing) => missing,
         Err(_) => {
             sp.finish_error("Error embedding".to_string());
-            Err(eyre!("Error embedding"))
+            return Err(eyre!("Error embedding"));
         }
+    };
+
+    let message = format!(
+        "{}/{} document(s) embedded",
+        doc_count - missing.len(),
+        doc_count
+    );
+    if missing.is_empty() {
+        sp.finish_ok(message);
+    } else {
+        sp.finish_error(message);
+        for location in missing.iter() {
+            println!("{}", format!("  ✗ {}", location).red());
+        }
+    }
+
+    Ok(())
+}
+
+/// Embed the uploaded documents at `locations` in a workspace, and check that they landed.
+///
+/// A successful response from the backend doesn't mean that every document was vectorized, so
+/// the workspace's documents are fetched again and compared with `locations`.
+/// Missing documents are re-submitted up to `retries` times. Returns the locations of the
+/// documents that are still missing.
+pub async fn embed_and_verify(
+    backend: &dyn RetrievalBackend,
+    slug: &str,
+    locations: Vec<String>,
+    retries: u32,
+) -> Result<Vec<String>, LLMError> {
+    let mut pending = locations;
+
+    for attempt in 0..=retries {
+        if pending.is_empty() {
+            break;
+        }
+        if attempt > 0 {
+            event!(
+                Level::WARN,
+                "retrying {} document(s) that failed to embed",
+                pending.len()
+            );
+        }
+
+        backend.embed_documents(slug, pending.clone()).await?;
+
+        let embedded = backend.workspace_documents(slug).await?;
+        pending = missing_documents(&pending, &embedded);
     }
+
+    Ok(pending)
+}
+
+// The locations that don't appear among a workspace's embedded documents
+fn missing_documents(locations: &[String], embedded: &[WorkspaceDocument]) -> Vec<String> {
+    let embedded: HashSet<&str> = embedded.iter().map(|d| d.docpath.as_str()).collect();
+
+    locations
+        .iter()
+        .filter(|location| !embedded.contains(location.as_str()))
+        .cloned()
+        .collect()
 }
 
-pub fn file_paths(pdfs: Vec<Item>) -> Vec<PathBuf> {
-    let config = Config::from_file().unwrap();
-    let zotero_library_root_path = PathBuf::from(config.zotero_library_root_path);
+/// The paths of the PDFs' files under the library's `storage_path`.
+pub fn file_paths(storage_path: &Path, pdfs: Vec<Item>) -> Vec<PathBuf> {
     let file_paths = pdfs
         .iter()
-        .map(|pdf| pdf.filepath(&zotero_library_root_path).unwrap())
+        .map(|pdf| pdf.filepath(storage_path).unwrap())
         .collect::<Vec<PathBuf>>();
     file_paths
 }
@@ -2,13 +2,12 @@ use eyre::Context;
 
 use crate::anythingllm::workspace::Workspace;
 use crate::app;
-use crate::app::commands;
+use crate::backend::RetrievalBackend;
 
 /// List all workspaces.
-pub async fn list() -> eyre::Result<()> {
-    let client = commands::anythingllm_client();
-    let workspaces = client
-        .get_workspaces()
+pub async fn list(backend: &dyn RetrievalBackend) -> eyre::Result<()> {
+    let workspaces = backend
+        .workspaces()
         .await
         .wrap_err("couldn't get workspaces")?;
 
@@ -1,11 +1,21 @@
 pub use chat::chat;
+pub use config::configure;
 pub use create::create;
 pub use delete::{delete, delete_all};
+pub use docs::docs;
+pub use history::history;
 pub use import::{import_folder, import_item, import_zotero};
 pub use list::list;
+pub use pin::pin;
+pub use threads::threads;
 
 pub mod chat;
+pub mod config;
 pub mod create;
 pub mod delete;
+pub mod docs;
+pub mod history;
 pub mod import;
 pub mod list;
+pub mod pin;
+pub mod threads;
@@ -0,0 +1,41 @@
+use colored::*;
+
+use crate::app::commands;
+
+/// Pin or unpin a document in a workspace.
+///
+/// The document is identified by its title or filename. A pinned document is included in full
+/// in the context of every chat with the workspace.
+pub async fn pin(workspace_name: String, document_name: String, pinned: bool) -> eyre::Result<()> {
+    let client = commands::anythingllm_client()?;
+    let workspace = client.get_workspace_by_name(&workspace_name).await?;
+    let document = client
+        .get_workspace_document_by_name(&workspace.slug, &document_name)
+        .await?;
+
+    if document.pinned == pinned {
+        let state = if pinned {
+            "already pinned"
+        } else {
+            "not pinned"
+        };
+        println!("'{}' is {}", document.metadata.title, state);
+        return Ok(());
+    }
+
+    client
+        .update_pin(&workspace.slug, &document.docpath, pinned)
+        .await?;
+
+    let action = if pinned { "Pinned" } else { "Unpinned" };
+    println!(
+        "{}",
+        format!(
+            "{} '{}' in workspace '{}'",
+            action, document.metadata.title, workspace.name
+        )
+        .green()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use eyre::Context;
+
+use crate::anythingllm::thread::Thread;
+use crate::app;
+use crate::app::commands;
+
+/// List the threads in a workspace.
+pub async fn threads(workspace_name: String) -> eyre::Result<()> {
+    let client = commands::anythingllm_client()?;
+    let workspace = client.get_workspace_by_name(&workspace_name).await?;
+    let threads = client
+        .get_threads(&workspace.slug)
+        .await
+        .wrap_err("couldn't get threads")?;
+
+    if threads.is_empty() {
+        println!("No threads found");
+        return Ok(());
+    }
+
+    let column_titles = vec!["THREAD", "SLUG"];
+    let data = data_from_threads(threads);
+    app::display_table(column_titles, data);
+
+    Ok(())
+}
+
+fn data_from_threads(threads: Vec<Thread>) -> Vec<Vec<String>> {
+    threads
+        .iter()
+        .map(|t| vec![t.name.clone(), t.slug.clone()])
+        .collect()
+}
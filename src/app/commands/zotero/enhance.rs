@@ -1,19 +1,23 @@
 use crate::anythingllm::workspace::models::Workspace;
 use crate::anythingllm::{ChatMode, Document};
-use crate::app::commands;
 use crate::app::commands::workspace::import::{
-    file_paths, get_collection, get_pdfs_from_collection, UpdateParameter,
+    get_collection, get_pdfs_from_collection, read_library,
 };
-use crate::zotero::item::models::{Item, ItemUpdateData, Tag};
+use crate::app::EnhanceOutput;
+use crate::backend::RetrievalBackend;
+use crate::zotero::cache::LibraryCache;
+use crate::zotero::client::ZoteroClient;
+use crate::zotero::item::models::{Item, ItemUpdate, ItemUpdateData, Tag};
 use crate::Config;
 use colored::Colorize;
 use dialoguer::Confirm;
 use futures::{stream, StreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex};
+use tokio::time::Instant;
 use tracing::{event, info, instrument, span, Instrument, Level};
 use uuid::Uuid;
 
@@ -23,12 +27,24 @@ use uuid::Uuid;
 /// item data from the Zotero API, creates a workspace for it and embeds it in the workspace. It
 /// interrogates the item to obtain its title and author, and generates an abstract and keywords.
 /// It updates the item metadata in the Zotero database, marks it with a tag to mark it as processed,
-/// and deletes the custom workspace.
+/// and deletes the custom workspace and the uploaded PDF. Updates are collected and written in
+/// batches of up to `MAX_WRITE_OBJECTS` items.
+///
+/// As many PDFs are enhanced at once as the backend allows chats.
+///
+/// If `output` is `EnhanceOutput::Note`, the abstract and keywords are instead written to a new
+/// child note of the item, headed with the model, date and prompts that generated them, and the
+/// item itself is left unchanged.
 ///
 /// NOTE: This function alters a Zotero database and is not reversible. Use at own discretion.
 ///
-#[instrument]
-pub async fn enhance_collection(collection_name: String) -> eyre::Result<()> {
+#[instrument(skip(backend, zotero))]
+pub async fn enhance_collection(
+    backend: &dyn RetrievalBackend,
+    zotero: &ZoteroClient,
+    collection_name: String,
+    output: EnhanceOutput,
+) -> eyre::Result<()> {
     let confirm = Confirm::new()
         .with_prompt(format!(
             "This will modify Zotero collection '{}' and cannot be undone. Are you sure you wish to proceed?",
@@ -41,21 +57,23 @@ pub async fn enhance_collection(collection_name: String) -> eyre::Result<()> {
         return Ok(());
     }
 
-    let collection = match get_collection(&collection_name).await {
+    let library = read_library(zotero).await?;
+
+    let collection = match get_collection(&library, &collection_name) {
         Ok(collection) => collection,
         Err(e) => {
             return Err(e);
         }
     };
 
-    let pdfs = match get_pdfs_from_collection(&collection).await {
+    let pdfs = match get_pdfs_from_collection(&library, &collection) {
         Ok(docs) => docs,
         Err(e) => {
             return Err(e);
         }
     };
 
-    match enhance_pdfs(pdfs).await {
+    match enhance_pdfs(backend, zotero, &library, pdfs, output).await {
         Ok(_) => (),
         Err(e) => {
             return Err(e);
@@ -65,7 +83,13 @@ pub async fn enhance_collection(collection_name: String) -> eyre::Result<()> {
     Ok(())
 }
 
-async fn enhance_pdfs(pdfs: Vec<Item>) -> eyre::Result<Vec<String>> {
+async fn enhance_pdfs(
+    backend: &dyn RetrievalBackend,
+    zotero: &ZoteroClient,
+    library: &LibraryCache,
+    pdfs: Vec<Item>,
+    output: EnhanceOutput,
+) -> eyre::Result<Vec<String>> {
     let mut failures = Vec::<Item>::new();
 
     let doc_count = pdfs.len();
@@ -75,52 +99,79 @@ async fn enhance_pdfs(pdfs: Vec<Item>) -> eyre::Result<Vec<String>> {
         .unwrap();
     bar.set_style(bar_style.progress_chars("##-"));
 
-    // share one copy between multiple readers using reference counting
-    let zotero = Arc::new(commands::zotero_client());
+    let storage_path = Config::from_file()?.zotero_storage_path(&zotero.library);
     let failed_docs = Arc::new(Mutex::new(Vec::<Item>::new()));
+    let pauses = tokio::spawn(show_pauses(zotero.throttle.subscribe(), bar.clone()));
+    let model = backend.model().await;
+    let concurrency = backend.limits().chats.max(1);
 
-    let docs: Vec<_> = stream::iter(pdfs)
+    let enhanced = stream::iter(pdfs)
         .map(|pdf| {
             let span = span!(Level::INFO, "enhance PDF");
-            let zotero = zotero.clone();
+            let storage_path = &storage_path;
             let failed_docs = failed_docs.clone();
             let bar = bar.clone();
 
             async move {
                 event!(Level::INFO, "Getting metadata for {}", pdf.title);
-                let metadata = match get_metadata(pdf.clone()).await {
+                match get_metadata(backend, storage_path, pdf.clone()).await {
                     Ok(m) => {
                         bar.inc(1);
                         event!(Level::INFO, "Got metadata: {:?}", m);
-                        m
+                        Some((pdf, m))
                     }
                     Err(_) => {
                         bar.inc(1);
                         let mut failed_docs = failed_docs.lock().await;
                         failed_docs.push(pdf.clone());
                         event!(Level::INFO, "metadata failed");
-                        return None;
-                    }
-                };
-
-                event!(Level::INFO, "Updating parent item for  {}", pdf.title);
-                match zotero.change_parent_item(&pdf, &metadata).await {
-                    Ok(_) => Some(pdf.title),
-                    Err(_) => {
-                        let mut failed_docs = failed_docs.lock().await;
-                        failed_docs.push(pdf.clone());
-                        event!(Level::INFO, "upload fail: {}", pdf.title,);
                         None
                     }
                 }
             }
             .instrument(span)
         })
-        .buffered(20)
-        .filter_map(|f| async { f })
-        .collect()
-        .await;
+        .buffered(concurrency)
+        .filter_map(|f| async { f });
+
+    let docs: Vec<String> = match output {
+        // the updates are based on the versions of the parent items in the library, and are
+        // written once every PDF is enhanced, so that each parent is written once with the
+        // metadata of all of its PDFs
+        EnhanceOutput::Fields => {
+            let enhanced: Vec<(Item, Metadata)> = enhanced.collect().await;
+            update_parent_items(zotero, library, enhanced, &failed_docs).await
+        }
+        EnhanceOutput::Note => {
+            enhanced
+                .then(|(pdf, metadata)| {
+                    let model = model.as_deref();
+                    let failed_docs = failed_docs.clone();
+
+                    async move {
+                        event!(Level::INFO, "Adding note to parent item for  {}", pdf.title);
+                        let note = metadata.note(model, chrono::Local::now());
+                        let tags = vec![Tag {
+                            tag: AYDA_TAG.to_string(),
+                        }];
+                        match zotero.create_child_note(&pdf, note, tags).await {
+                            Ok(_) => Some(pdf.title),
+                            Err(e) => {
+                                let mut failed_docs = failed_docs.lock().await;
+                                failed_docs.push(pdf.clone());
+                                event!(Level::WARN, "upload fail: {}: {}", pdf.title, e);
+                                None
+                            }
+                        }
+                    }
+                })
+                .filter_map(|f| async { f })
+                .collect()
+                .await
+        }
+    };
 
+    pauses.abort();
     bar.finish();
 
     let failed_docs_mutex_guard = failed_docs.lock().await;
@@ -150,63 +201,207 @@ async fn enhance_pdfs(pdfs: Vec<Item>) -> eyre::Result<Vec<String>> {
     Ok(docs)
 }
 
-/// Enhance a PDF item.
-async fn get_metadata(pdf: Item) -> eyre::Result<ItemUpdateData> {
-    let anythingllm = commands::anythingllm_client();
-    let workspace_name = format!("workspace_{}", Uuid::new_v4());
-    let workspace = anythingllm.create_workspace(&workspace_name).await?;
+// Update the parent items of the PDFs with their metadata, in batches of up to
+// `MAX_WRITE_OBJECTS` items, and return the titles of the PDFs whose parent was updated.
+//
+// PDFs of the same parent are merged into one update with the keywords of all of them and the
+// abstract of the first, as updating a parent again would replace its tags.
+async fn update_parent_items(
+    zotero: &ZoteroClient,
+    library: &LibraryCache,
+    enhanced: Vec<(Item, Metadata)>,
+    failed_docs: &Mutex<Vec<Item>>,
+) -> Vec<String> {
+    let mut updates = Vec::new();
+    let mut pdfs = Vec::new();
+    for (pdf, metadata) in enhanced {
+        let parent = pdf
+            .parent_item
+            .as_ref()
+            .and_then(|key| library.items.get(key));
+        match parent {
+            Some(parent) => {
+                updates.push(ItemUpdate {
+                    key: parent.key.clone(),
+                    version: parent.version,
+                    data: metadata.update_data(),
+                });
+                pdfs.push(pdf);
+            }
+            None => {
+                event!(Level::WARN, "upload fail: {}: no parent item", pdf.title);
+                failed_docs.lock().await.push(pdf);
+            }
+        }
+    }
 
-    let config = Config::from_file()?;
-    let zotero_library_root_path = PathBuf::from(config.zotero_library_root_path);
-    let document_filepath = match pdf
-        .filepath(&zotero_library_root_path)
-        .ok_or(eyre::eyre!("No file path"))
-    {
-        Ok(p) => p,
-        Err(_) => {
-            anythingllm.delete_workspace_slug(&workspace.slug).await?;
-            return Err(eyre::eyre!("No file path"));
+    event!(Level::INFO, "Updating {} parent items", updates.len());
+    let results = zotero.update_items_batch(updates).await;
+
+    let mut updated = Vec::new();
+    for pdf in pdfs {
+        let failure = results
+            .iter()
+            .filter(|r| pdf.parent_item.as_ref() == Some(&r.key))
+            .find(|r| !r.result.is_ok());
+        match failure {
+            None => updated.push(pdf.title),
+            Some(failure) => {
+                event!(
+                    Level::WARN,
+                    "upload fail: {}: {:?}",
+                    pdf.title,
+                    failure.result
+                );
+                failed_docs.lock().await.push(pdf);
+            }
         }
-    };
+    }
 
-    let doc = match anythingllm.post_document_upload(&document_filepath).await {
-        Ok(d) => d,
-        Err(_) => {
-            anythingllm.delete_workspace_slug(&workspace.slug).await?;
-            return Err(eyre::eyre!("Document upload failed"));
+    updated
+}
+
+// Show pauses requested by the Zotero API in the progress bar's message until they pass
+async fn show_pauses(mut pauses: watch::Receiver<Option<Instant>>, bar: ProgressBar) {
+    while pauses.changed().await.is_ok() {
+        let Some(until) = *pauses.borrow_and_update() else {
+            continue;
+        };
+        let wait = until.saturating_duration_since(Instant::now());
+        bar.set_message(format!(
+            "(Zotero asked us to wait {}s)",
+            wait.as_secs().max(1)
+        ));
+
+        tokio::time::sleep_until(until).await;
+        if !pauses.has_changed().unwrap_or(false) {
+            bar.set_message("");
         }
-    };
+    }
+}
+
+/// The tag that marks items processed by ayda.
+const AYDA_TAG: &str = "ayda";
 
-    match anythingllm
-        .update_embeddings(
-            &workspace.slug,
-            vec![doc.clone().location.unwrap()],
-            UpdateParameter::Adds,
+/// The abstract and keywords generated for a PDF, and the prompts that generated them.
+#[derive(Debug, Clone)]
+struct Metadata {
+    summary: String,
+    keywords: Vec<String>,
+    prompts: Vec<String>,
+}
+
+impl Metadata {
+    /// The fields to update the PDF's parent item with: its abstract, and the keywords as tags.
+    fn update_data(&self) -> ItemUpdateData {
+        let mut tags: Vec<Tag> = self
+            .keywords
+            .iter()
+            .map(|tag| Tag { tag: tag.clone() })
+            .collect();
+
+        tags.push(Tag {
+            tag: AYDA_TAG.to_string(),
+        });
+
+        ItemUpdateData {
+            abstract_note: Some(self.summary.clone()),
+            tags: Some(tags),
+            ..Default::default()
+        }
+    }
+
+    /// A note holding the abstract and keywords as HTML, headed with where they came from.
+    fn note(&self, model: Option<&str>, date: chrono::DateTime<chrono::Local>) -> String {
+        let prompts: String = self
+            .prompts
+            .iter()
+            .map(|prompt| format!("<li>{}</li>", escape_html(prompt)))
+            .collect();
+
+        format!(
+            "<h1>Summary</h1>\n\
+             <p><em>Generated by ayda with {} on {}, from the prompts:</em></p>\n\
+             <ul>{}</ul>\n\
+             <p>{}</p>\n\
+             <h2>Keywords</h2>\n\
+             <p>{}</p>",
+            escape_html(model.unwrap_or("an unknown model")),
+            date.format("%Y-%m-%d %H:%M"),
+            prompts,
+            escape_html(&self.summary),
+            escape_html(&self.keywords.join(", ")),
         )
+    }
+}
+
+/// Enhance a PDF item, in a workspace of its own that is deleted afterwards with the upload.
+async fn get_metadata(
+    backend: &dyn RetrievalBackend,
+    storage_path: &Path,
+    pdf: Item,
+) -> eyre::Result<Metadata> {
+    let document_filepath = pdf
+        .filepath(storage_path)
+        .ok_or(eyre::eyre!("No file path"))?;
+
+    let doc = match backend.upload_document(&document_filepath).await {
+        Ok(d) => d,
+        Err(_) => return Err(eyre::eyre!("Document upload failed")),
+    };
+    let location = doc
+        .location
+        .clone()
+        .ok_or(eyre::eyre!("No document location"))?;
+
+    let metadata = interrogate_in_workspace(backend, &pdf, &doc, &location).await;
+
+    if let Err(e) = backend.delete_documents(vec![location]).await {
+        event!(
+            Level::WARN,
+            "unable to delete upload of {}: {}",
+            pdf.title,
+            e
+        );
+    }
+
+    metadata
+}
+
+// Embed an uploaded PDF in a new workspace, interrogate it, and delete the workspace
+async fn interrogate_in_workspace(
+    backend: &dyn RetrievalBackend,
+    pdf: &Item,
+    doc: &Document,
+    location: &str,
+) -> eyre::Result<Metadata> {
+    let workspace_name = format!("workspace_{}", Uuid::new_v4());
+    let workspace = backend.create_workspace(&workspace_name).await?;
+
+    let metadata = match backend
+        .embed_documents(&workspace.slug, vec![location.to_string()])
         .await
     {
-        Ok(_) => (),
-        Err(_) => {
-            anythingllm.delete_workspace_slug(&workspace.slug).await?;
-            return Err(eyre::eyre!("Embedding failed"));
+        Ok(_) => {
+            info!(
+                "Embedded document {} in workspace {}",
+                pdf.title, workspace.slug
+            );
+            Ok(interrogate_doc(backend, &workspace, doc).await)
         }
+        Err(_) => Err(eyre::eyre!("Embedding failed")),
     };
 
-    info!(
-        "Embedded document {} in workspace {}",
-        pdf.title, workspace.slug
-    );
-
-    let update_data = interrogate_doc(&workspace, &doc).await;
-
-    anythingllm.delete_workspace_slug(&workspace.slug).await?;
+    backend.delete_workspace(&workspace.slug).await?;
 
-    Ok(update_data)
+    metadata
 }
 
-async fn interrogate_doc(workspace: &Workspace, doc: &Document) -> ItemUpdateData {
-    let anythingllm = commands::anythingllm_client();
-
+async fn interrogate_doc(
+    backend: &dyn RetrievalBackend,
+    workspace: &Workspace,
+    doc: &Document,
+) -> Metadata {
     let doc_title = doc.clone().title.unwrap();
     let abstract_query = format!(
         "Summarise '{}' in 300 words. Omit the title, author, and line breaks.",
@@ -214,16 +409,16 @@ async fn interrogate_doc(workspace: &Workspace, doc: &Document) -> ItemUpdateDat
     );
     let keywords_query = format!("[INST]This is an academic article[/INST] Generate 3 keywords for {}. Display the result as a comma separated list", &doc_title);
 
-    let doc_abstract = anythingllm
-        .post_workspace_slug_chat(&workspace.slug, &abstract_query, &ChatMode::Chat)
+    let doc_abstract = backend
+        .chat(&workspace.slug, None, &abstract_query, &ChatMode::Chat)
         .await
         .unwrap();
 
     // remove \n from abstract
     let doc_abstract = doc_abstract.text_response.replace('\n', "");
 
-    let keywords = anythingllm
-        .post_workspace_slug_chat(&workspace.slug, &keywords_query, &ChatMode::Chat)
+    let keywords = backend
+        .chat(&workspace.slug, None, &keywords_query, &ChatMode::Chat)
         .await
         .unwrap();
 
@@ -231,21 +426,20 @@ async fn interrogate_doc(workspace: &Workspace, doc: &Document) -> ItemUpdateDat
         .text_response
         .split(',')
         .map(|s| s.trim().to_string())
-        .collect();
-
-    let mut tags: Vec<Tag> = keywords
-        .iter()
         .take(3)
-        .map(|tag| Tag { tag: tag.clone() })
         .collect();
 
-    tags.push(Tag {
-        tag: "ayda".to_string(),
-    });
-
-    ItemUpdateData {
-        abstract_note: Some(doc_abstract),
-        tags: Some(tags),
-        ..Default::default()
+    Metadata {
+        summary: doc_abstract,
+        keywords,
+        prompts: vec![abstract_query, keywords_query],
     }
 }
+
+// Escape text for inclusion in HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
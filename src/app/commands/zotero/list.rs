@@ -1,28 +1,97 @@
 use itertools::Itertools;
 
 use crate::app;
-use crate::app::commands;
-use crate::zotero::collection::models::Collection;
+use crate::zotero::client::ZoteroClient;
+use crate::zotero::collection::models::{Collection, CollectionNode};
+use crate::zotero::group::models::GroupsResponse;
+use crate::zotero::source::ZoteroSource;
 
-/// List all Zotero collections.
+/// List all Zotero collections, with subcollections indented under their parents.
 ///
-pub async fn list_collections() -> eyre::Result<()> {
-    let client = commands::zotero_client();
-    let collections = client.get_collections(None).await?;
-    let column_titles = vec!["COLLECTION NAME"];
-    let data = data_from_collections(collections);
+/// If `collection_name` is given, only that collection and its subcollections are listed. It may
+/// be a `parent/child` path to pick out a subcollection whose name isn't unique.
+///
+/// Collections are read from the local cache of the library, which is refreshed first, or from the
+/// Zotero desktop app's database.
+pub async fn list_collections(
+    client: &dyn ZoteroSource,
+    collection_name: Option<String>,
+) -> eyre::Result<()> {
+    let library = client.read_library().await?;
+    let tree = match collection_name {
+        Some(name) => {
+            let collection = library.collection_from_name(&name)?;
+            let collections = library.collections();
+            let keys = Collection::descendant_keys(&collections, &collection.key);
+            let subtree: Vec<Collection> = collections
+                .into_iter()
+                .filter(|c| keys.contains(&c.key))
+                .map(|c| match c.key == collection.key {
+                    // make the collection the root of the tree
+                    true => Collection {
+                        parent_collection: None,
+                        ..c
+                    },
+                    false => c,
+                })
+                .collect();
+            Collection::tree(&subtree)
+        }
+        None => library.collection_tree(),
+    };
+
+    let column_titles = vec!["COLLECTION NAME", "ITEMS"];
+    let data = data_from_collection_tree(&tree);
     app::display_table(column_titles, data);
 
     Ok(())
 }
 
-// Extract data, sort alphabetically, convert to lowercase, and return as a Vec<Vec<String>>.
-fn data_from_collections(collections: Vec<Collection>) -> Vec<Vec<String>> {
-    let data: Vec<Vec<String>> = collections
-        .iter()
-        .map(|c| vec![c.name.to_lowercase().clone()])
-        .sorted_by(|a, b| a[0].cmp(&b[0]))
-        .collect();
+/// List the Zotero groups the user belongs to.
+///
+/// A group's library can be used with `--library group:<GROUP_ID>`.
+pub async fn list_groups(client: &ZoteroClient) -> eyre::Result<()> {
+    let groups = client.get_groups().await?;
+    let column_titles = vec!["GROUP ID", "GROUP NAME", "ITEMS"];
+    let data = data_from_groups(groups);
+    app::display_table(column_titles, data);
+
+    Ok(())
+}
+
+// Extract data in tree order, indenting subcollections under their parents, convert to lowercase,
+// and return as a Vec<Vec<String>>.
+fn data_from_collection_tree(tree: &[CollectionNode]) -> Vec<Vec<String>> {
+    fn add_rows(nodes: &[CollectionNode], depth: usize, data: &mut Vec<Vec<String>>) {
+        for node in nodes {
+            data.push(vec![
+                format!(
+                    "{}{}",
+                    "  ".repeat(depth),
+                    node.collection.name.to_lowercase()
+                ),
+                node.collection.num_items.to_string(),
+            ]);
+            add_rows(&node.children, depth + 1, data);
+        }
+    }
 
+    let mut data = Vec::new();
+    add_rows(tree, 0, &mut data);
     data
 }
+
+// Extract the ID, name and item count of each group, sorted by name.
+fn data_from_groups(groups: Vec<GroupsResponse>) -> Vec<Vec<String>> {
+    groups
+        .iter()
+        .sorted_by_key(|g| g.data.name.to_lowercase())
+        .map(|g| {
+            vec![
+                g.id.to_string(),
+                g.data.name.clone(),
+                g.meta.num_items.map(|n| n.to_string()).unwrap_or_default(),
+            ]
+        })
+        .collect()
+}
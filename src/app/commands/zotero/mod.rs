@@ -1,5 +1,7 @@
 pub use enhance::enhance_collection;
-pub use list::list_collections;
+pub use list::{list_collections, list_groups};
+pub use refresh::refresh;
 
 pub mod enhance;
 pub mod list;
+pub mod refresh;
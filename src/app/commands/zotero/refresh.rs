@@ -0,0 +1,35 @@
+use colored::Colorize;
+
+use crate::zotero::cache::LibraryCache;
+use crate::zotero::client::ZoteroClient;
+
+/// Bring the local cache of the Zotero library up to date.
+///
+/// Only the items and collections changed since the last refresh are downloaded, unless `full` is
+/// set, in which case the cache is discarded and the whole library downloaded again.
+pub async fn refresh(client: &ZoteroClient, full: bool) -> eyre::Result<()> {
+    let path = client.cache_path();
+    let mut cache = match full {
+        true => LibraryCache::default(),
+        false => LibraryCache::load(&path)?,
+    };
+
+    let summary = client.refresh_cache(&mut cache).await?;
+    cache.save(&path)?;
+
+    let message = match summary.is_unchanged() {
+        true => format!("  Library is up to date (version {})", summary.version),
+        false => format!(
+            "  Library updated to version {}: {} collection(s) and {} item(s) changed, {} deleted",
+            summary.version, summary.collections, summary.items, summary.deleted
+        ),
+    };
+    println!("{}", message.green());
+    println!(
+        "  {} collection(s) and {} item(s) cached",
+        cache.collections.len(),
+        cache.items.len()
+    );
+
+    Ok(())
+}
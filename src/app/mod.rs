@@ -1,6 +1,13 @@
 //! A command line application.
 
-use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::anythingllm::ChatMode;
+use crate::backend::BackendType;
+use crate::zotero::library::Library;
+use crate::zotero::source::ZoteroSourceType;
 
 pub mod commands;
 
@@ -12,6 +19,44 @@ pub mod commands;
 pub struct Cli {
     #[clap(subcommand)]
     pub command: Commands,
+
+    #[clap(flatten)]
+    pub backend: BackendArgs,
+
+    #[clap(flatten)]
+    pub zotero: ZoteroArgs,
+}
+
+/// Options for the retrieval backend, overriding the configuration file.
+#[derive(Args, Clone, Debug, Default)]
+pub struct BackendArgs {
+    /// The retrieval backend to use
+    #[clap(value_enum, long, global = true)]
+    pub backend: Option<BackendType>,
+
+    /// Maximum number of concurrent document uploads to AnythingLLM
+    #[clap(long, global = true)]
+    pub max_uploads: Option<usize>,
+
+    /// Maximum number of concurrent chat requests to AnythingLLM
+    #[clap(long, global = true)]
+    pub max_chats: Option<usize>,
+
+    /// Maximum number of requests per second to AnythingLLM
+    #[clap(long, global = true)]
+    pub requests_per_second: Option<f64>,
+}
+
+/// Options for the Zotero library, overriding the configuration file.
+#[derive(Args, Clone, Debug, Default)]
+pub struct ZoteroArgs {
+    /// The Zotero library to use: 'user' or 'group:<GROUP_ID>'
+    #[clap(long, global = true)]
+    pub library: Option<Library>,
+
+    /// Where to read the Zotero library from: the web API, or the Zotero desktop app's database
+    #[clap(value_enum, long, global = true)]
+    pub zotero_source: Option<ZoteroSourceType>,
 }
 
 #[derive(Subcommand)]
@@ -43,12 +88,24 @@ pub enum Commands {
 
         /// The source to import from
         source_name: String,
+
+        /// Number of times to retry documents that fail to embed
+        #[clap(long, default_value_t = 1)]
+        retries: u32,
+
+        /// Include the items in the collection's subcollections
+        #[clap(long)]
+        recursive: bool,
     },
 
     /// Chat with a workspace
     Chat {
         /// Name of the workspace to chat with
         workspace_name: String,
+
+        /// Chat in the thread with this name, creating it if it doesn't exist
+        #[clap(long)]
+        thread: Option<String>,
     },
 
     /// Query a workspace
@@ -57,6 +114,79 @@ pub enum Commands {
         workspace_name: String,
     },
 
+    /// List the threads in a workspace
+    Threads {
+        /// Name of the workspace
+        workspace_name: String,
+    },
+
+    /// List the documents in a workspace
+    Docs {
+        /// Name of the workspace
+        workspace_name: String,
+
+        /// Sort documents by this field
+        #[clap(value_enum, long, default_value = "title")]
+        sort: DocSortKey,
+
+        /// Reverse the sort order
+        #[clap(long)]
+        reverse: bool,
+
+        /// Only show documents whose title or author contains this text
+        #[clap(long)]
+        filter: Option<String>,
+
+        /// Only show pinned documents
+        #[clap(long)]
+        pinned: bool,
+    },
+
+    /// Pin a document in a workspace so that its full text is used in every chat
+    Pin {
+        /// Name of the workspace
+        workspace_name: String,
+
+        /// Title or filename of the document to pin
+        document_name: String,
+    },
+
+    /// Unpin a document in a workspace
+    Unpin {
+        /// Name of the workspace
+        workspace_name: String,
+
+        /// Title or filename of the document to unpin
+        document_name: String,
+    },
+
+    /// Show or export the chat history of a workspace
+    History {
+        /// Name of the workspace
+        workspace_name: String,
+
+        /// Export the history in this format
+        #[clap(value_enum, long)]
+        format: Option<HistoryFormat>,
+
+        /// File to export to (defaults to stdout)
+        #[clap(long, short, requires = "format")]
+        output: Option<PathBuf>,
+    },
+
+    /// Show the configuration and health of the AnythingLLM instance
+    Status {
+        /// Print the status as JSON
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Manage workspace settings
+    Workspace {
+        #[clap(subcommand)]
+        command: WorkspaceCmd,
+    },
+
     /// Manage Zotero collections
     Zotero {
         #[clap(subcommand)]
@@ -67,15 +197,69 @@ pub enum Commands {
     Config {},
 }
 
+#[derive(Subcommand)]
+pub enum WorkspaceCmd {
+    /// Show or change the settings of a workspace
+    Config {
+        /// Name of the workspace to configure
+        workspace_name: String,
+
+        /// Minimum similarity score for a document chunk to be used as a source
+        #[clap(long)]
+        similarity_threshold: Option<f64>,
+
+        /// LLM temperature
+        #[clap(long)]
+        temperature: Option<f64>,
+
+        /// Number of previous chat messages to include in the context
+        #[clap(long)]
+        history_length: Option<u32>,
+
+        /// Maximum number of document chunks to use as sources
+        #[clap(long)]
+        top_n: Option<u32>,
+
+        /// System prompt sent to the LLM
+        #[clap(long)]
+        system_prompt: Option<String>,
+
+        /// Default chat mode ('chat' or 'query')
+        #[clap(long)]
+        chat_mode: Option<ChatMode>,
+
+        /// Edit the settings in an editor
+        #[clap(long, conflicts_with_all = ["similarity_threshold", "temperature", "history_length", "top_n", "system_prompt", "chat_mode"])]
+        edit: bool,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum ZoteroCmd {
-    /// List all Zotero collections
-    ListCollections,
+    /// List all Zotero collections and their subcollections
+    ListCollections {
+        /// Only list this collection and its subcollections, e.g. 'climate' or 'climate/paleo'
+        collection_name: Option<String>,
+    },
+
+    /// List the Zotero groups you belong to
+    ListGroups,
+
+    /// Update the local cache of the Zotero library
+    Refresh {
+        /// Discard the cache and download the whole library again
+        #[clap(long)]
+        full: bool,
+    },
 
     /// Enhance a collection
     Enhance {
         /// The name of the collection to enhance
         collection_name: String,
+
+        /// Where to write the generated abstract and keywords
+        #[clap(value_enum, long, default_value = "fields")]
+        output: EnhanceOutput,
     },
 }
 
@@ -89,6 +273,34 @@ pub enum SourceType {
     Item,
 }
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum EnhanceOutput {
+    /// Overwrite the item's abstract and tags
+    Fields,
+    /// Add a child note to the item, leaving the item unchanged
+    Note,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+pub enum DocSortKey {
+    /// Sort by title
+    Title,
+    /// Sort by author
+    Author,
+    /// Sort by word count
+    Words,
+    /// Sort by date added to the workspace
+    Created,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+pub enum HistoryFormat {
+    /// Markdown document
+    Markdown,
+    /// JSON array of exchanges
+    Json,
+}
+
 // Utility to create a table from headers and data
 // e.g.
 //
@@ -101,11 +313,12 @@ fn display_table(column_titles: Vec<&str>, data: Vec<Vec<String>>) {
     let data_style = console::Style::new();
 
     // find the longest string in each column
-    let mut column_widths = [0, 0, 0];
+    let mut column_widths: Vec<usize> = column_titles.iter().map(|t| t.len()).collect();
     for row in data.iter() {
         for (i, cell) in row.iter().enumerate() {
-            if cell.len() > column_widths[i] {
-                column_widths[i] = cell.len();
+            let len = cell.chars().count();
+            if len > column_widths[i] {
+                column_widths[i] = len;
             }
         }
     }
@@ -0,0 +1,117 @@
+//! The AnythingLLM retrieval backend.
+
+use std::path::Path;
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+
+use crate::anythingllm::client::AnythingLLMClient;
+use crate::anythingllm::error::LLMError;
+use crate::anythingllm::limiter::Limits;
+use crate::anythingllm::thread::Thread;
+use crate::anythingllm::workspace::{
+    ChatMode, PostWorkspaceSlugChatResponse, StreamChatChunk, Workspace, WorkspaceDocument,
+};
+use crate::anythingllm::Document;
+use crate::app::commands::workspace::import::UpdateParameter;
+use crate::backend::RetrievalBackend;
+
+#[async_trait]
+impl RetrievalBackend for AnythingLLMClient {
+    async fn workspaces(&self) -> Result<Vec<Workspace>, LLMError> {
+        self.get_workspaces().await
+    }
+
+    async fn workspace_by_name(&self, name: &str) -> Result<Workspace, LLMError> {
+        self.get_workspace_by_name(name).await
+    }
+
+    async fn create_workspace(&self, name: &str) -> Result<Workspace, LLMError> {
+        AnythingLLMClient::create_workspace(self, name).await
+    }
+
+    async fn delete_workspace(&self, slug: &str) -> Result<(), LLMError> {
+        self.delete_workspace_slug(slug).await
+    }
+
+    async fn upload_document(&self, path: &Path) -> Result<Document, LLMError> {
+        self.post_document_upload(path).await
+    }
+
+    async fn upload_link(&self, link: &str) -> Result<Document, LLMError> {
+        self.post_document_upload_link(link).await
+    }
+
+    async fn embed_documents(&self, slug: &str, locations: Vec<String>) -> Result<(), LLMError> {
+        self.update_embeddings(slug, locations, UpdateParameter::Adds)
+            .await
+    }
+
+    async fn delete_documents(&self, locations: Vec<String>) -> Result<(), LLMError> {
+        self.delete_api_system_remove_documents(locations).await
+    }
+
+    async fn workspace_documents(&self, slug: &str) -> Result<Vec<WorkspaceDocument>, LLMError> {
+        self.get_workspace_documents(slug).await
+    }
+
+    async fn chat(
+        &self,
+        slug: &str,
+        thread_slug: Option<&str>,
+        message: &str,
+        mode: &ChatMode,
+    ) -> Result<PostWorkspaceSlugChatResponse, LLMError> {
+        match thread_slug {
+            Some(thread_slug) => {
+                self.post_thread_chat(slug, thread_slug, message, mode)
+                    .await
+            }
+            None => self.post_workspace_slug_chat(slug, message, mode).await,
+        }
+    }
+
+    async fn stream_chat<'a>(
+        &'a self,
+        slug: &'a str,
+        thread_slug: Option<&'a str>,
+        message: &'a str,
+        mode: &'a ChatMode,
+    ) -> Result<BoxStream<'a, Result<StreamChatChunk, LLMError>>, LLMError> {
+        let chunks = match thread_slug {
+            Some(thread_slug) => self
+                .stream_thread_chat(slug, thread_slug, message, mode)
+                .await?
+                .boxed(),
+            None => self
+                .stream_workspace_slug_chat(slug, message, mode)
+                .await?
+                .boxed(),
+        };
+
+        Ok(chunks)
+    }
+
+    fn limits(&self) -> Limits {
+        self.limiter.limits().clone()
+    }
+
+    async fn model(&self) -> Option<String> {
+        self.get_system().await.ok()?.llm_model()
+    }
+
+    async fn thread(&self, slug: &str, name: &str) -> Result<(Thread, bool), LLMError> {
+        match self.get_thread_by_name(slug, name).await {
+            Ok(thread) => Ok((thread, false)),
+            Err(LLMError::ThreadNotFoundError(_)) => {
+                Ok((self.create_thread(slug, name).await?, true))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn delete_all_workspaces(&self) -> Result<(), LLMError> {
+        AnythingLLMClient::delete_all_workspaces(self).await
+    }
+}
@@ -0,0 +1,67 @@
+//! Text extraction and chunking for the local backend.
+
+use std::path::Path;
+
+use crate::anythingllm::error::LLMError;
+
+/// Extract the text of a PDF, text or Markdown file.
+pub fn extract_text(path: &Path) -> Result<String, LLMError> {
+    let extension = path
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .unwrap_or_default()
+        .to_lowercase();
+
+    match extension.as_str() {
+        "pdf" => {
+            let doc = lopdf::Document::load(path)?;
+            let pages: Vec<u32> = doc.get_pages().keys().cloned().collect();
+            Ok(doc.extract_text(&pages)?)
+        }
+        "txt" | "md" => Ok(std::fs::read_to_string(path)?),
+        _ => Err(LLMError::UnsupportedError(format!(
+            "file type of {}",
+            path.display()
+        ))),
+    }
+}
+
+/// Split text into chunks of `size` words, each overlapping the previous one by `overlap` words.
+pub fn chunk_text(text: &str, size: usize, overlap: usize) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let step = size.saturating_sub(overlap).max(1);
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < words.len() {
+        let end = (start + size).min(words.len());
+        chunks.push(words[start..end].join(" "));
+        if end == words.len() {
+            break;
+        }
+        start += step;
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::chunk_text;
+
+    #[test]
+    fn test_chunk_text_overlaps() {
+        let text = "one two three four five six seven";
+        let chunks = chunk_text(text, 3, 1);
+
+        assert_eq!(
+            chunks,
+            vec!["one two three", "three four five", "five six seven"]
+        );
+    }
+
+    #[test]
+    fn test_chunk_text_empty() {
+        assert!(chunk_text("  \n ", 3, 1).is_empty());
+    }
+}
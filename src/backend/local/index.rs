@@ -0,0 +1,246 @@
+//! A BM25 full-text index over the document chunks in a workspace.
+
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// BM25 parameters
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+const STOP_WORDS: [&str; 24] = [
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "in", "is", "it", "of", "on",
+    "or", "that", "the", "this", "to", "was", "were", "with", "what",
+];
+
+/// A document in the index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedDocument {
+    pub location: String,
+    pub title: String,
+    pub path: String,
+    pub word_count: u32,
+    pub added_at: DateTime<Utc>,
+}
+
+/// A passage of a document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    /// The position of the chunk's document in `Index::documents`.
+    pub document: usize,
+    pub text: String,
+    /// The number of terms in the chunk.
+    pub length: u32,
+}
+
+/// A chunk that matches a query, with its BM25 score.
+#[derive(Debug)]
+pub struct Hit<'a> {
+    pub document: &'a IndexedDocument,
+    pub chunk: &'a Chunk,
+    pub score: f64,
+}
+
+/// An inverted index from terms to the chunks that contain them.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Index {
+    pub documents: Vec<IndexedDocument>,
+    pub chunks: Vec<Chunk>,
+    /// Term -> (chunk, term frequency)
+    postings: HashMap<String, Vec<(usize, u32)>>,
+    total_length: u64,
+}
+
+impl Index {
+    /// Load an index, or return an empty one if it doesn't exist yet.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        match std::fs::read(path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    /// Return true if the document at `location` has been indexed.
+    pub fn contains(&self, location: &str) -> bool {
+        self.documents.iter().any(|d| d.location == location)
+    }
+
+    /// Add a document and its chunks to the index.
+    pub fn add_document(&mut self, document: IndexedDocument, chunks: Vec<String>) {
+        let document_id = self.documents.len();
+        self.documents.push(document);
+
+        for text in chunks {
+            let chunk_id = self.chunks.len();
+            let terms = tokenize(&text);
+
+            let mut frequencies = HashMap::<String, u32>::new();
+            for term in terms.iter() {
+                *frequencies.entry(term.clone()).or_default() += 1;
+            }
+            for (term, frequency) in frequencies {
+                self.postings
+                    .entry(term)
+                    .or_default()
+                    .push((chunk_id, frequency));
+            }
+
+            self.total_length += terms.len() as u64;
+            self.chunks.push(Chunk {
+                document: document_id,
+                text,
+                length: terms.len() as u32,
+            });
+        }
+    }
+
+    /// Remove the documents at `locations` and their chunks from the index.
+    pub fn remove_documents(&mut self, locations: &[String]) {
+        let old = std::mem::take(self);
+        let mut chunks: Vec<Vec<String>> = vec![Vec::new(); old.documents.len()];
+        for chunk in old.chunks {
+            chunks[chunk.document].push(chunk.text);
+        }
+
+        for (document, chunks) in old.documents.into_iter().zip(chunks) {
+            if !locations.contains(&document.location) {
+                self.add_document(document, chunks);
+            }
+        }
+    }
+
+    /// The `limit` chunks that best match the query, best first.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<Hit<'_>> {
+        if self.chunks.is_empty() {
+            return Vec::new();
+        }
+
+        let chunk_count = self.chunks.len() as f64;
+        let average_length = self.total_length as f64 / chunk_count;
+
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+
+        let mut scores = HashMap::<usize, f64>::new();
+        for term in terms {
+            let Some(postings) = self.postings.get(&term) else {
+                continue;
+            };
+            let df = postings.len() as f64;
+            let idf = ((chunk_count - df + 0.5) / (df + 0.5) + 1.0).ln();
+
+            for (chunk_id, frequency) in postings {
+                let tf = *frequency as f64;
+                let length = self.chunks[*chunk_id].length as f64;
+                let norm = K1 * (1.0 - B + B * length / average_length);
+                *scores.entry(*chunk_id).or_default() += idf * tf * (K1 + 1.0) / (tf + norm);
+            }
+        }
+
+        let mut scores: Vec<(usize, f64)> = scores.into_iter().collect();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        scores
+            .into_iter()
+            .take(limit)
+            .map(|(chunk_id, score)| {
+                let chunk = &self.chunks[chunk_id];
+                Hit {
+                    document: &self.documents[chunk.document],
+                    chunk,
+                    score,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Split text into lowercase terms, dropping stop words and single characters.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() > 1)
+        .map(str::to_lowercase)
+        .filter(|t| !STOP_WORDS.contains(&t.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::{tokenize, Index, IndexedDocument};
+
+    fn document(location: &str) -> IndexedDocument {
+        IndexedDocument {
+            location: location.to_string(),
+            title: location.to_string(),
+            path: location.to_string(),
+            word_count: 0,
+            added_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("The Holocene: an interglacial, 11,700 years."),
+            vec!["holocene", "interglacial", "11", "700", "years"]
+        );
+    }
+
+    #[test]
+    fn test_search_ranks_matching_chunks() {
+        let mut index = Index::default();
+        index.add_document(
+            document("berger"),
+            vec![
+                "Interglacials of the last 800,000 years".to_string(),
+                "Orbital forcing and interglacial interglacial timing".to_string(),
+            ],
+        );
+        index.add_document(
+            document("covid"),
+            vec!["Transmission of respiratory viruses".to_string()],
+        );
+
+        let hits = index.search("interglacial timing", 10);
+
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].document.location, "berger");
+        assert!(hits[0].chunk.text.starts_with("Orbital"));
+        assert!(index.search("glaciology", 10).is_empty());
+    }
+
+    #[test]
+    fn test_remove_documents() {
+        let mut index = Index::default();
+        index.add_document(
+            document("berger"),
+            vec!["Interglacials of the last 800,000 years".to_string()],
+        );
+        index.add_document(
+            document("covid"),
+            vec!["Transmission of respiratory viruses".to_string()],
+        );
+
+        index.remove_documents(&["berger".to_string()]);
+
+        assert!(!index.contains("berger"));
+        assert!(index.search("interglacials", 10).is_empty());
+        let hits = index.search("viruses", 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].document.location, "covid");
+    }
+}
@@ -0,0 +1,432 @@
+//! An offline retrieval backend.
+//!
+//! Documents are stored and indexed in-process, so no AnythingLLM instance is needed. PDF text is
+//! extracted with `lopdf` and split into overlapping chunks, and each workspace has a BM25 index
+//! of its chunks that is persisted under the data directory:
+//!
+//! ```text
+//! <root>/workspaces.json        the workspaces
+//! <root>/documents/<id>.json    uploaded documents, with their chunks
+//! <root>/indexes/<slug>.json    the index of each workspace
+//! ```
+//!
+//! There is no language model, so both chat modes return the passages that best match the
+//! message as sources, with their scores.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::anythingllm::error::LLMError;
+use crate::anythingllm::workspace::{
+    ChatMode, PostWorkspaceSlugChatResponse, Source, StreamChatChunk, Workspace, WorkspaceDocument,
+    WorkspaceDocumentMetadata,
+};
+use crate::anythingllm::Document;
+use crate::backend::local::extract::{chunk_text, extract_text};
+use crate::backend::local::index::{Hit, Index, IndexedDocument};
+use crate::backend::RetrievalBackend;
+
+pub mod extract;
+pub mod index;
+
+const CHUNK_SIZE: usize = 200;
+const CHUNK_OVERLAP: usize = 40;
+const TOP_N: usize = 4;
+
+/// A workspace, as stored in `workspaces.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WorkspaceRecord {
+    id: u32,
+    name: String,
+    slug: String,
+    created_at: DateTime<Utc>,
+}
+
+impl From<&WorkspaceRecord> for Workspace {
+    fn from(record: &WorkspaceRecord) -> Self {
+        Workspace {
+            id: record.id,
+            name: record.name.clone(),
+            slug: record.slug.clone(),
+            created_at: record.created_at,
+            last_updated_at: record.created_at,
+            similarity_threshold: 0.0,
+            chat_mode: ChatMode::Query,
+            temperature: None,
+            history_length: None,
+            system_prompt: None,
+            top_n: Some(TOP_N as u32),
+            documents: None,
+            threads: None,
+        }
+    }
+}
+
+/// An uploaded document and its chunks.
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredDocument {
+    id: String,
+    title: String,
+    path: String,
+    word_count: u32,
+    chunks: Vec<String>,
+}
+
+/// The offline backend, storing its data under `root`.
+#[derive(Debug)]
+pub struct LocalBackend {
+    root: PathBuf,
+    // serialises changes to `workspaces.json`
+    lock: Mutex<()>,
+}
+
+impl LocalBackend {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// The default location of the backend's data.
+    pub fn default_root() -> PathBuf {
+        let dirs = directories_next::ProjectDirs::from("com", "richardlyon", "aza").unwrap();
+        dirs.data_dir().join("local")
+    }
+
+    // helper functions ///////////////////////////////////////////////////////////////////////
+
+    fn workspaces_path(&self) -> PathBuf {
+        self.root.join("workspaces.json")
+    }
+
+    fn index_path(&self, slug: &str) -> PathBuf {
+        self.root.join("indexes").join(format!("{}.json", slug))
+    }
+
+    fn document_path(&self, location: &str) -> PathBuf {
+        self.root.join(location)
+    }
+
+    fn read_workspaces(&self) -> Result<Vec<WorkspaceRecord>, LLMError> {
+        match std::fs::read(self.workspaces_path()) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| LLMError::ServiceError(format!("invalid workspaces file: {}", e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write_workspaces(&self, workspaces: &[WorkspaceRecord]) -> Result<(), LLMError> {
+        std::fs::create_dir_all(&self.root)?;
+        std::fs::write(
+            self.workspaces_path(),
+            serde_json::to_vec_pretty(workspaces).unwrap(),
+        )?;
+        Ok(())
+    }
+
+    fn read_index(&self, slug: &str) -> Result<Index, LLMError> {
+        Ok(Index::load(&self.index_path(slug))?)
+    }
+
+    fn read_document(&self, location: &str) -> Result<StoredDocument, LLMError> {
+        let bytes = std::fs::read(self.document_path(location))
+            .map_err(|_| LLMError::DocumentNotFoundWorkspaceError(location.to_string()))?;
+        serde_json::from_slice(&bytes)
+            .map_err(|e| LLMError::ServiceError(format!("invalid document {}: {}", location, e)))
+    }
+
+    // Check that the workspace exists
+    fn workspace(&self, slug: &str) -> Result<WorkspaceRecord, LLMError> {
+        self.read_workspaces()?
+            .into_iter()
+            .find(|w| w.slug == slug)
+            .ok_or(LLMError::WorkspaceIdError(slug.to_string()))
+    }
+
+    fn slugify(name: &str, existing: &[WorkspaceRecord]) -> String {
+        let slug = name
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+
+        let mut candidate = slug.clone();
+        let mut n = 1;
+        while existing.iter().any(|w| w.slug == candidate) {
+            n += 1;
+            candidate = format!("{}-{}", slug, n);
+        }
+        candidate
+    }
+
+    fn workspace_document(
+        id: usize,
+        workspace: &WorkspaceRecord,
+        document: &IndexedDocument,
+    ) -> WorkspaceDocument {
+        let filename = Path::new(&document.path)
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        WorkspaceDocument {
+            id: id as u32,
+            doc_id: document.location.clone(),
+            workspace_id: workspace.id,
+            created_at: document.added_at,
+            last_updated_at: document.added_at,
+            docpath: document.location.clone(),
+            filename,
+            pinned: false,
+            metadata: WorkspaceDocumentMetadata {
+                id: document.location.clone(),
+                title: document.title.clone(),
+                doc_source: document.path.clone(),
+                word_count: document.word_count,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn source(hit: &Hit) -> Source {
+        Source {
+            id: hit.document.location.clone(),
+            title: hit.document.title.clone(),
+            doc_source: hit.document.path.clone(),
+            text: hit.chunk.text.clone(),
+            score: hit.score,
+            word_count: hit.chunk.text.split_whitespace().count() as u32,
+            ..Default::default()
+        }
+    }
+
+    // Describe the matching passages, best first
+    fn response_text(sources: &[Source]) -> String {
+        if sources.is_empty() {
+            return "No relevant passages found.".to_string();
+        }
+
+        sources
+            .iter()
+            .enumerate()
+            .map(|(i, s)| format!("[{}] {} (score {:.2})\n{}", i + 1, s.title, s.score, s.text))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+#[async_trait]
+impl RetrievalBackend for LocalBackend {
+    async fn workspaces(&self) -> Result<Vec<Workspace>, LLMError> {
+        Ok(self
+            .read_workspaces()?
+            .iter()
+            .map(Workspace::from)
+            .collect())
+    }
+
+    async fn workspace_by_name(&self, name: &str) -> Result<Workspace, LLMError> {
+        let workspaces = self.read_workspaces()?;
+        let matching: Vec<_> = workspaces.iter().filter(|w| w.name == name).collect();
+
+        match matching.len() {
+            0 => Err(LLMError::WorkspaceIdError(name.to_string())),
+            1 => Ok(matching[0].into()),
+            _ => Err(LLMError::MultipleWorkspacesError(name.to_string())),
+        }
+    }
+
+    async fn create_workspace(&self, name: &str) -> Result<Workspace, LLMError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut workspaces = self.read_workspaces()?;
+
+        let record = WorkspaceRecord {
+            id: workspaces.iter().map(|w| w.id + 1).max().unwrap_or(1),
+            name: name.to_string(),
+            slug: Self::slugify(name, &workspaces),
+            created_at: Utc::now(),
+        };
+        workspaces.push(record.clone());
+        self.write_workspaces(&workspaces)?;
+
+        Ok((&record).into())
+    }
+
+    async fn delete_workspace(&self, slug: &str) -> Result<(), LLMError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut workspaces = self.read_workspaces()?;
+        if !workspaces.iter().any(|w| w.slug == slug) {
+            return Err(LLMError::WorkspaceIdError(slug.to_string()));
+        }
+
+        // remove the workspace's documents, as AnythingLLM does
+        let index = self.read_index(slug)?;
+        for document in index.documents {
+            let _ = std::fs::remove_file(self.document_path(&document.location));
+        }
+        let _ = std::fs::remove_file(self.index_path(slug));
+
+        workspaces.retain(|w| w.slug != slug);
+        self.write_workspaces(&workspaces)
+    }
+
+    async fn upload_document(&self, path: &Path) -> Result<Document, LLMError> {
+        if !path.exists() {
+            return Err(LLMError::FileSystemError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} not found", path.display()),
+            )));
+        }
+
+        let file_path = path.to_path_buf();
+        let text = tokio::task::spawn_blocking(move || extract_text(&file_path))
+            .await
+            .map_err(|e| LLMError::UnhandledError(e.to_string()))??;
+
+        let id = Uuid::new_v4().to_string();
+        let title = path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let location = format!("documents/{}.json", id);
+
+        let document = StoredDocument {
+            id: id.clone(),
+            title: title.clone(),
+            path: path.display().to_string(),
+            word_count: text.split_whitespace().count() as u32,
+            chunks: chunk_text(&text, CHUNK_SIZE, CHUNK_OVERLAP),
+        };
+
+        let document_path = self.document_path(&location);
+        std::fs::create_dir_all(document_path.parent().unwrap())?;
+        std::fs::write(&document_path, serde_json::to_vec(&document).unwrap())?;
+
+        Ok(Document {
+            doc_id: id,
+            name: Some(title.clone()),
+            title: Some(title),
+            cached: None,
+            location: Some(location),
+        })
+    }
+
+    async fn upload_link(&self, _link: &str) -> Result<Document, LLMError> {
+        Err(LLMError::UnsupportedError("web links".to_string()))
+    }
+
+    async fn embed_documents(&self, slug: &str, locations: Vec<String>) -> Result<(), LLMError> {
+        let _guard = self.lock.lock().unwrap();
+        self.workspace(slug)?;
+        let mut index = self.read_index(slug)?;
+
+        for location in locations {
+            if index.contains(&location) {
+                continue;
+            }
+            let document = match self.read_document(&location) {
+                Ok(document) => document,
+                Err(e) => {
+                    tracing::warn!(location, error = %e, "unable to embed document");
+                    continue;
+                }
+            };
+
+            index.add_document(
+                IndexedDocument {
+                    location,
+                    title: document.title,
+                    path: document.path,
+                    word_count: document.word_count,
+                    added_at: Utc::now(),
+                },
+                document.chunks,
+            );
+        }
+
+        Ok(index.save(&self.index_path(slug))?)
+    }
+
+    async fn delete_documents(&self, locations: Vec<String>) -> Result<(), LLMError> {
+        let _guard = self.lock.lock().unwrap();
+        for workspace in self.read_workspaces()? {
+            let mut index = self.read_index(&workspace.slug)?;
+            if locations.iter().any(|location| index.contains(location)) {
+                index.remove_documents(&locations);
+                index.save(&self.index_path(&workspace.slug))?;
+            }
+        }
+
+        for location in locations {
+            let _ = std::fs::remove_file(self.document_path(&location));
+        }
+        Ok(())
+    }
+
+    async fn workspace_documents(&self, slug: &str) -> Result<Vec<WorkspaceDocument>, LLMError> {
+        let workspace = self.workspace(slug)?;
+        let index = self.read_index(slug)?;
+
+        Ok(index
+            .documents
+            .iter()
+            .enumerate()
+            .map(|(i, d)| Self::workspace_document(i, &workspace, d))
+            .collect())
+    }
+
+    async fn chat(
+        &self,
+        slug: &str,
+        _thread_slug: Option<&str>,
+        message: &str,
+        _mode: &ChatMode,
+    ) -> Result<PostWorkspaceSlugChatResponse, LLMError> {
+        self.workspace(slug)?;
+        let index = self.read_index(slug)?;
+
+        let sources: Vec<Source> = index
+            .search(message, TOP_N)
+            .iter()
+            .map(Self::source)
+            .collect();
+
+        Ok(PostWorkspaceSlugChatResponse {
+            id: Some(Uuid::new_v4().to_string()),
+            close: true,
+            error: None,
+            text_response: Self::response_text(&sources),
+            sources,
+            response_type: "textResponse".to_string(),
+            chat_id: None,
+        })
+    }
+
+    async fn stream_chat<'a>(
+        &'a self,
+        slug: &'a str,
+        thread_slug: Option<&'a str>,
+        message: &'a str,
+        mode: &'a ChatMode,
+    ) -> Result<BoxStream<'a, Result<StreamChatChunk, LLMError>>, LLMError> {
+        let response = self.chat(slug, thread_slug, message, mode).await?;
+
+        let chunks = vec![
+            Ok(StreamChatChunk::Text(response.text_response)),
+            Ok(StreamChatChunk::Sources(response.sources)),
+            Ok(StreamChatChunk::Close),
+        ];
+
+        Ok(futures::stream::iter(chunks).boxed())
+    }
+}
@@ -0,0 +1,130 @@
+//! Retrieval backends.
+//!
+//! A retrieval backend stores documents in workspaces, embeds them and answers chat messages
+//! using them. The commands in `app::commands` are written against the `RetrievalBackend` trait,
+//! so that they work with any backend and can be tested against in-memory doubles.
+//!
+//! ## Example usage:
+//!
+//! ```rust,no_run
+//! use ayda::anythingllm::client::AnythingLLMClient;
+//! use ayda::backend::RetrievalBackend;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let client = AnythingLLMClient::new("127.0.0.1", "3001", "api_key").unwrap();
+//!     let backend: &dyn RetrievalBackend = &client;
+//!     let workspaces = backend.workspaces().await.unwrap();
+//! }
+//! ```
+//!
+
+use std::path::Path;
+
+use async_trait::async_trait;
+use clap::ValueEnum;
+use futures::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+
+use crate::anythingllm::error::LLMError;
+use crate::anythingllm::limiter::Limits;
+use crate::anythingllm::thread::Thread;
+use crate::anythingllm::workspace::{
+    ChatMode, PostWorkspaceSlugChatResponse, StreamChatChunk, Workspace, WorkspaceDocument,
+};
+use crate::anythingllm::Document;
+
+pub mod anythingllm;
+pub mod local;
+
+/// The available retrieval backends.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendType {
+    /// An AnythingLLM instance
+    #[default]
+    #[value(name = "anythingllm")]
+    AnythingLLM,
+    /// The built-in offline backend, with a local full-text index
+    Local,
+}
+
+/// The operations ayda needs from a retrieval-augmented generation backend.
+///
+/// Workspaces are identified by their slug. Documents are uploaded first, which returns their
+/// location in the backend's document store, and are then embedded in a workspace by location.
+#[async_trait]
+pub trait RetrievalBackend: Send + Sync {
+    /// Get all workspaces.
+    async fn workspaces(&self) -> Result<Vec<Workspace>, LLMError>;
+
+    /// Get the workspace with name `name`, if it is unique.
+    async fn workspace_by_name(&self, name: &str) -> Result<Workspace, LLMError>;
+
+    /// Create a new workspace.
+    async fn create_workspace(&self, name: &str) -> Result<Workspace, LLMError>;
+
+    /// Delete a workspace and the documents embedded in it.
+    async fn delete_workspace(&self, slug: &str) -> Result<(), LLMError>;
+
+    /// Upload a file to the document store.
+    async fn upload_document(&self, path: &Path) -> Result<Document, LLMError>;
+
+    /// Upload the web page at `link` to the document store.
+    async fn upload_link(&self, link: &str) -> Result<Document, LLMError>;
+
+    /// Embed the uploaded documents at `locations` in a workspace.
+    async fn embed_documents(&self, slug: &str, locations: Vec<String>) -> Result<(), LLMError>;
+
+    /// Delete the uploaded documents at `locations` from the document store, and from any
+    /// workspace they are embedded in.
+    async fn delete_documents(&self, locations: Vec<String>) -> Result<(), LLMError>;
+
+    /// Get the documents embedded in a workspace.
+    async fn workspace_documents(&self, slug: &str) -> Result<Vec<WorkspaceDocument>, LLMError>;
+
+    /// Send a chat message to a workspace, or to one of its threads, and wait for the response.
+    async fn chat(
+        &self,
+        slug: &str,
+        thread_slug: Option<&str>,
+        message: &str,
+        mode: &ChatMode,
+    ) -> Result<PostWorkspaceSlugChatResponse, LLMError>;
+
+    /// Send a chat message to a workspace, or to one of its threads, and stream the response.
+    async fn stream_chat<'a>(
+        &'a self,
+        slug: &'a str,
+        thread_slug: Option<&'a str>,
+        message: &'a str,
+        mode: &'a ChatMode,
+    ) -> Result<BoxStream<'a, Result<StreamChatChunk, LLMError>>, LLMError>;
+
+    /// The limits on concurrent requests to the backend, which commands size their own
+    /// concurrency from.
+    fn limits(&self) -> Limits {
+        Limits::default()
+    }
+
+    /// The language model that answers chats, if the backend reports it.
+    async fn model(&self) -> Option<String> {
+        None
+    }
+
+    /// Get the thread in a workspace with name `name`, creating it if it doesn't exist.
+    ///
+    /// Returns the thread and whether it was created. Backends without threads return an error.
+    async fn thread(&self, slug: &str, name: &str) -> Result<(Thread, bool), LLMError> {
+        let _ = (slug, name);
+        Err(LLMError::UnsupportedError("threads".to_string()))
+    }
+
+    /// Delete every workspace.
+    async fn delete_all_workspaces(&self) -> Result<(), LLMError> {
+        for workspace in self.workspaces().await? {
+            self.delete_workspace(&workspace.slug).await?;
+        }
+        Ok(())
+    }
+}
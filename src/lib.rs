@@ -49,30 +49,113 @@
 
 extern crate core;
 
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use dialoguer::Input;
 use eyre::WrapErr;
 use serde::{Deserialize, Serialize};
 
+use crate::anythingllm::client::ClientOptions;
+use crate::anythingllm::limiter::Limits;
+use crate::anythingllm::retry::RetryPolicy;
 use crate::app::commands::admin;
+use crate::backend::BackendType;
+use crate::zotero::library::Library;
+use crate::zotero::local;
+use crate::zotero::source::ZoteroSourceType;
 
 pub mod anythingllm;
 pub mod app;
+pub mod backend;
 pub mod zotero;
 
 /// Configuration parameters for the application.
+///
+/// The AnythingLLM instance is located either by `anythingllm_base_url`, which may include a
+/// scheme and path prefix, or by `anythingllm_ip` and `anythingllm_port`.
+///
+/// Zotero commands work with the user's own library unless `zotero_library` selects a group. They
+/// read it through the web API unless `zotero_source` is `local`, when they read the Zotero desktop
+/// app's database at `zotero_database_path`.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
     pub zotero_user_id: String,
     pub zotero_api_key: String,
     pub zotero_library_root_path: String,
+    #[serde(default, skip_serializing_if = "Library::is_user")]
+    pub zotero_library: Library,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub zotero_group_storage_paths: BTreeMap<u64, String>,
+    #[serde(default)]
+    pub zotero_source: ZoteroSourceType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zotero_database_path: Option<String>,
     pub anythingllm_api_key: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub anythingllm_ip: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub anythingllm_port: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anythingllm_base_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anythingllm_ca_certificates: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anythingllm_timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anythingllm_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Limits::is_default")]
+    pub anythingllm_limits: Limits,
+    #[serde(default, skip_serializing_if = "RetryPolicy::is_default")]
+    pub anythingllm_retry: RetryPolicy,
+    #[serde(default)]
+    pub backend: BackendType,
 }
 
 impl Config {
+    /// The base URL of the AnythingLLM instance.
+    pub fn anythingllm_base_url(&self) -> String {
+        match &self.anythingllm_base_url {
+            Some(base_url) => base_url.clone(),
+            None => format!("http://{}:{}", self.anythingllm_ip, self.anythingllm_port),
+        }
+    }
+
+    /// The connection options for the AnythingLLM client.
+    pub fn anythingllm_options(&self) -> ClientOptions {
+        ClientOptions {
+            ca_certificates: self.anythingllm_ca_certificates.clone(),
+            timeout: self.anythingllm_timeout_secs.map(Duration::from_secs),
+            proxy: self.anythingllm_proxy.clone(),
+            limits: self.anythingllm_limits.clone(),
+            retry_policy: self.anythingllm_retry.clone(),
+        }
+    }
+
+    /// The directory holding the attachment files of a Zotero library.
+    ///
+    /// Zotero keeps the files of every library in the same storage directory, so this is
+    /// `zotero_library_root_path` unless a group's files are kept elsewhere.
+    pub fn zotero_storage_path(&self, library: &Library) -> PathBuf {
+        let path = match library {
+            Library::Group(group_id) => self.zotero_group_storage_paths.get(group_id),
+            Library::User => None,
+        };
+        PathBuf::from(path.unwrap_or(&self.zotero_library_root_path))
+    }
+
+    /// The Zotero desktop app's database.
+    ///
+    /// Unless `zotero_database_path` is set, this is `zotero.sqlite` in the Zotero data directory,
+    /// which holds the storage directory at `zotero_library_root_path`.
+    pub fn zotero_database_path(&self) -> PathBuf {
+        match &self.zotero_database_path {
+            Some(path) => PathBuf::from(path),
+            None => local::default_database_path(Path::new(&self.zotero_library_root_path)),
+        }
+    }
+
     pub fn from_file() -> eyre::Result<Self> {
         let config_path = Config::get_config_path();
         let file = std::fs::File::open(config_path)?;
@@ -118,13 +201,9 @@ pub fn get_config_parameters() -> Config {
         .interact_text()
         .unwrap();
 
-    let anythingllm_ip = Input::new()
-        .with_prompt("AnythingLLM IP")
-        .interact_text()
-        .unwrap();
-
-    let anythingllm_port = Input::new()
-        .with_prompt("AnythingLLM Port")
+    let anythingllm_base_url = Input::new()
+        .with_prompt("AnythingLLM URL")
+        .default("http://localhost:3001".to_string())
         .interact_text()
         .unwrap();
 
@@ -132,8 +211,19 @@ pub fn get_config_parameters() -> Config {
         zotero_user_id,
         zotero_api_key,
         zotero_library_root_path,
+        zotero_library: Library::default(),
+        zotero_group_storage_paths: BTreeMap::new(),
+        zotero_source: ZoteroSourceType::default(),
+        zotero_database_path: None,
         anythingllm_api_key,
-        anythingllm_ip,
-        anythingllm_port,
+        anythingllm_ip: String::new(),
+        anythingllm_port: String::new(),
+        anythingllm_base_url: Some(anythingllm_base_url),
+        anythingllm_ca_certificates: Vec::new(),
+        anythingllm_timeout_secs: None,
+        anythingllm_proxy: None,
+        anythingllm_limits: Limits::default(),
+        anythingllm_retry: RetryPolicy::default(),
+        backend: BackendType::default(),
     }
 }
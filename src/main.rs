@@ -10,10 +10,12 @@ use color_eyre::owo_colors::OwoColorize;
 use eyre::Context;
 use tokio::select;
 use tracing::Level;
-use tracing_subscriber::{EnvFilter, FmtSubscriber};
+use tracing_subscriber::FmtSubscriber;
 
-use ayda::app::{commands::admin, commands::workspace, commands::zotero, Commands::*};
-use ayda::app::{Cli, SourceType, ZoteroCmd};
+use ayda::anythingllm::WorkspaceSettings;
+use ayda::app::commands::{self, admin, status, workspace, zotero};
+use ayda::app::Commands::*;
+use ayda::app::{Cli, SourceType, WorkspaceCmd, ZoteroCmd};
 
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
@@ -34,7 +36,7 @@ async fn main() -> eyre::Result<()> {
     let cli = Cli::parse();
 
     select! {
-        _ = command(config_path, cli) => {},
+        result = command(config_path, cli) => result?,
         _ = tokio::signal::ctrl_c() => {
             tracing::info!("detected interrupt, exiting");
         },
@@ -45,19 +47,31 @@ async fn main() -> eyre::Result<()> {
 
 #[tracing::instrument(skip(config_path, cli))]
 async fn command(config_path: PathBuf, cli: Cli) -> eyre::Result<()> {
+    let backend_args = cli.backend;
+    let zotero_args = cli.zotero;
+
     match cli.command {
-        Create { workspace_name } => workspace::create::create(workspace_name)
-            .await
-            .wrap_err("unable to create workspace"),
+        Create { workspace_name } => {
+            let backend = commands::backend(&backend_args)?;
+            workspace::create::create(&*backend, workspace_name)
+                .await
+                .wrap_err("unable to create workspace")
+        }
 
-        List {} => workspace::list().await.wrap_err("unable to list workspace"),
+        List {} => {
+            let backend = commands::backend(&backend_args)?;
+            workspace::list(&*backend)
+                .await
+                .wrap_err("unable to list workspace")
+        }
 
         Delete {
             workspace_name: Some(name),
             all: false,
         } => {
             // Delete a specific workspace
-            match workspace::delete(name).await {
+            let backend = commands::backend(&backend_args)?;
+            match workspace::delete(&*backend, name).await {
                 Ok(_) => Ok(()),
                 Err(e) => {
                     println!("{}", e.to_string().red());
@@ -71,7 +85,8 @@ async fn command(config_path: PathBuf, cli: Cli) -> eyre::Result<()> {
             all: true,
         } => {
             // Delete all workspaces
-            match workspace::delete_all().await {
+            let backend = commands::backend(&backend_args)?;
+            match workspace::delete_all(&*backend).await {
                 Ok(_) => Ok(()),
                 Err(e) => {
                     println!("{}", e.to_string().red());
@@ -83,42 +98,158 @@ async fn command(config_path: PathBuf, cli: Cli) -> eyre::Result<()> {
         Import {
             source,
             source_name,
+            retries,
+            recursive,
         } => match source {
-            SourceType::Zotero {} => workspace::import_zotero(source_name)
-                .await
-                .wrap_err("unable to import zotero collection"),
-            SourceType::Folder {} => workspace::import_folder(PathBuf::from(source_name))
-                .await
-                .wrap_err("unable to import file"),
-            SourceType::Item {} => workspace::import_item()
+            SourceType::Zotero => {
+                let backend = commands::backend(&backend_args)?;
+                let zotero = commands::zotero_source(&zotero_args)?;
+                workspace::import_zotero(&*backend, &*zotero, source_name, retries, recursive)
+                    .await
+                    .wrap_err("unable to import zotero collection")
+            }
+            SourceType::Folder => {
+                let backend = commands::backend(&backend_args)?;
+                workspace::import_folder(&*backend, PathBuf::from(source_name), retries)
+                    .await
+                    .wrap_err("unable to import file")
+            }
+            SourceType::Item => workspace::import_item()
                 .await
                 .wrap_err("unable to import item"),
         },
 
-        Chat { workspace_name } => workspace::chat(workspace_name, ChatMode::Chat)
+        Chat {
+            workspace_name,
+            thread,
+        } => {
+            let backend = commands::backend(&backend_args)?;
+            workspace::chat(&*backend, workspace_name, ChatMode::Chat, thread)
+                .await
+                .wrap_err("unable to chat with workspace")
+        }
+
+        Query { workspace_name } => {
+            let backend = commands::backend(&backend_args)?;
+            workspace::chat(&*backend, workspace_name, ChatMode::Query, None)
+                .await
+                .wrap_err("unable to query workspace")
+        }
+
+        Threads { workspace_name } => workspace::threads(workspace_name)
+            .await
+            .wrap_err("unable to list threads"),
+
+        Docs {
+            workspace_name,
+            sort,
+            reverse,
+            filter,
+            pinned,
+        } => {
+            let backend = commands::backend(&backend_args)?;
+            workspace::docs(&*backend, workspace_name, sort, reverse, filter, pinned)
+                .await
+                .wrap_err("unable to list workspace documents")
+        }
+
+        Pin {
+            workspace_name,
+            document_name,
+        } => workspace::pin(workspace_name, document_name, true)
             .await
-            .wrap_err("unable to chat with workspace"),
+            .wrap_err("unable to pin document"),
 
-        Query { workspace_name } => workspace::chat(workspace_name, ChatMode::Query)
+        Unpin {
+            workspace_name,
+            document_name,
+        } => workspace::pin(workspace_name, document_name, false)
             .await
-            .wrap_err("unable to query workspace"),
+            .wrap_err("unable to unpin document"),
+
+        History {
+            workspace_name,
+            format,
+            output,
+        } => workspace::history(workspace_name, format, output)
+            .await
+            .wrap_err("unable to get chat history"),
+
+        Status { json } => status::status(json).await.wrap_err("unable to get status"),
+
+        Workspace {
+            command:
+                WorkspaceCmd::Config {
+                    workspace_name,
+                    similarity_threshold,
+                    temperature,
+                    history_length,
+                    top_n,
+                    system_prompt,
+                    chat_mode,
+                    edit,
+                },
+        } => {
+            let settings = WorkspaceSettings {
+                similarity_threshold,
+                temperature,
+                history_length,
+                top_n,
+                system_prompt,
+                chat_mode,
+            };
+            workspace::configure(workspace_name, settings, edit)
+                .await
+                .wrap_err("unable to configure workspace")
+        }
 
         Zotero {
-            command: ZoteroCmd::ListCollections,
-        } => zotero::list_collections().await.wrap_err("unable to list Zotero collections"),
+            command: ZoteroCmd::ListCollections { collection_name },
+        } => {
+            let zotero = commands::zotero_source(&zotero_args)?;
+            zotero::list_collections(&*zotero, collection_name)
+                .await
+                .wrap_err("unable to list Zotero collections")
+        }
 
         Zotero {
-            command: ZoteroCmd::Enhance { collection_name },
-        } => zotero::enhance_collection(collection_name)
-            .await
-            .wrap_err("unable to enhance collection"),
+            command: ZoteroCmd::ListGroups,
+        } => {
+            let zotero = commands::zotero_client(&zotero_args)?;
+            zotero::list_groups(&zotero)
+                .await
+                .wrap_err("unable to list Zotero groups")
+        }
+
+        Zotero {
+            command: ZoteroCmd::Refresh { full },
+        } => {
+            let zotero = commands::zotero_client(&zotero_args)?;
+            zotero::refresh(&zotero, full)
+                .await
+                .wrap_err("unable to refresh Zotero library")
+        }
+
+        Zotero {
+            command:
+                ZoteroCmd::Enhance {
+                    collection_name,
+                    output,
+                },
+        } => {
+            let backend = commands::backend(&backend_args)?;
+            let zotero = commands::zotero_client(&zotero_args)?;
+            zotero::enhance_collection(&*backend, &zotero, collection_name, output)
+                .await
+                .wrap_err("unable to enhance collection")
+        }
 
-        Config {} => admin::configure(&config_path)
-            .wrap_err("unable to configure application"),
+        Config {} => admin::configure(&config_path).wrap_err("unable to configure application"),
 
         _ => {
             println!(
-                "Invalid combination of arguments. Please consult the documentation for available commands"
+                "Invalid combination of arguments. \
+                 Please consult the documentation for available commands"
             );
             Ok(())
         }
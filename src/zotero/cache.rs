@@ -0,0 +1,333 @@
+//! A local cache of a Zotero library's items and collections.
+//!
+//! The cache records the library version it reflects. Refreshing it requests only the objects
+//! modified since that version (`since=`), and the API answers `304 Not Modified` to the
+//! `If-Modified-Since-Version` header if nothing has changed at all. Objects deleted from the
+//! library are removed using the `deleted` endpoint.
+//!
+//! see: [Syncing](https://www.zotero.org/support/dev/web_api/v3/syncing).
+//!
+//! Notes are not cached.
+//!
+//! The cache is stamped with `CACHE_FORMAT`. A cache written in an older format is discarded when
+//! it's loaded, so that the next refresh fetches the whole library with every field the current
+//! format holds.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::zotero::client::ZoteroClient;
+use crate::zotero::collection::models::{Collection, CollectionNode, CollectionsResponse};
+use crate::zotero::error::ZoteroError;
+use crate::zotero::item::models::{Item, ItemsResponse};
+
+/// The format of the cache. Bump it when the cached models gain fields that a refresh of only the
+/// modified objects wouldn't fill in.
+pub const CACHE_FORMAT: u32 = 1;
+
+/// The items and collections of a library, as of library `version`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LibraryCache {
+    /// The format the cache was written in, or `0` for caches from before formats were recorded.
+    #[serde(default)]
+    pub format: u32,
+    /// The library version the cache reflects, or `0` if it has never been refreshed.
+    pub version: i64,
+    /// Collections by key.
+    pub collections: BTreeMap<String, Collection>,
+    /// Items by key.
+    pub items: BTreeMap<String, Item>,
+}
+
+impl Default for LibraryCache {
+    fn default() -> Self {
+        Self {
+            format: CACHE_FORMAT,
+            version: 0,
+            collections: BTreeMap::new(),
+            items: BTreeMap::new(),
+        }
+    }
+}
+
+/// The changes made by a refresh.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RefreshSummary {
+    /// The library version the cache now reflects.
+    pub version: i64,
+    /// The number of collections added or modified.
+    pub collections: usize,
+    /// The number of items added or modified.
+    pub items: usize,
+    /// The number of collections and items removed.
+    pub deleted: usize,
+}
+
+impl RefreshSummary {
+    /// Return true if the library hadn't changed.
+    pub fn is_unchanged(&self) -> bool {
+        self.collections == 0 && self.items == 0 && self.deleted == 0
+    }
+}
+
+/// Structure to deserialize the response from the API.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DeletedResponse {
+    #[serde(default)]
+    pub collections: Vec<String>,
+    #[serde(default)]
+    pub items: Vec<String>,
+}
+
+impl LibraryCache {
+    /// Read the cache at `path`, or return an empty cache if there is none or it was written in
+    /// an older format.
+    pub fn load(path: &Path) -> Result<Self, ZoteroError> {
+        let cache: Self = match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| {
+                ZoteroError::CacheError(format!("invalid cache {}: {}", path.display(), e))
+            })?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(ZoteroError::CacheError(e.to_string())),
+        };
+
+        if cache.format != CACHE_FORMAT {
+            tracing::info!(
+                format = cache.format,
+                "Zotero cache is in an old format, refreshing the whole library"
+            );
+            return Ok(Self::default());
+        }
+
+        Ok(cache)
+    }
+
+    /// Write the cache to `path`.
+    pub fn save(&self, path: &Path) -> Result<(), ZoteroError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| ZoteroError::CacheError(e.to_string()))?;
+        }
+        std::fs::write(path, serde_json::to_vec(self).unwrap())
+            .map_err(|e| ZoteroError::CacheError(e.to_string()))
+    }
+
+    /// All collections in the library.
+    pub fn collections(&self) -> Vec<Collection> {
+        self.collections.values().cloned().collect()
+    }
+
+    /// All collections in the library, arranged as trees of their subcollections.
+    pub fn collection_tree(&self) -> Vec<CollectionNode> {
+        Collection::tree(&self.collections())
+    }
+
+    /// Return a matching collection if collection_name corresponds to exactly one collection
+    /// NOTE: Case insensitive so 'COVID' matches 'covid'. The name may be a `parent/child` path.
+    pub fn collection_from_name(&self, collection_name: &str) -> Result<Collection, ZoteroError> {
+        Collection::find_by_name(&self.collections(), collection_name)
+    }
+
+    /// The items in a collection, including child items such as attachments.
+    pub fn collection_items(&self, collection_key: &str) -> Vec<Item> {
+        self.items_in(&[collection_key.to_string()])
+    }
+
+    /// The items in a collection and all of its subcollections.
+    pub fn collection_items_recursive(&self, collection_key: &str) -> Vec<Item> {
+        self.items_in(&Collection::descendant_keys(
+            &self.collections(),
+            collection_key,
+        ))
+    }
+
+    // The items in any of the collections, including child items such as attachments
+    fn items_in(&self, collection_keys: &[String]) -> Vec<Item> {
+        let in_collection =
+            |item: &Item| item.collections.iter().any(|k| collection_keys.contains(k));
+
+        self.items
+            .values()
+            .filter(|item| match &item.parent_item {
+                Some(parent) => self.items.get(parent).is_some_and(in_collection),
+                None => in_collection(item),
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+impl ZoteroClient {
+    /// GET /deleted?since=<version>
+    ///
+    /// The keys of objects deleted from the library since `version`
+    pub async fn get_deleted(&self, version: i64) -> Result<DeletedResponse, ZoteroError> {
+        let since = version.to_string();
+        self.get_deserialized::<DeletedResponse>("deleted", Some(vec![("since", &since)]))
+            .await
+    }
+
+    /// Bring `cache` up to date with the library.
+    pub async fn refresh_cache(
+        &self,
+        cache: &mut LibraryCache,
+    ) -> Result<RefreshSummary, ZoteroError> {
+        let since = cache.version;
+        let collections = self
+            .get_modified_since::<CollectionsResponse>("collections", None, since)
+            .await?;
+        let items = self
+            .get_modified_since::<ItemsResponse>("items", Some(vec![("itemType", "-note")]), since)
+            .await?;
+
+        // the library may change between requests, so take the earliest version reported to be
+        // sure of fetching those changes next time
+        let version = [
+            collections.as_ref().map(|(_, version)| *version),
+            items.as_ref().map(|(_, version)| *version),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(since);
+
+        let mut summary = RefreshSummary {
+            version,
+            ..Default::default()
+        };
+
+        if let Some((collections, _)) = collections {
+            summary.collections = collections.len();
+            for collection in collections {
+                let collection = collection.collection();
+                cache.collections.insert(collection.key.clone(), collection);
+            }
+        }
+
+        if let Some((items, _)) = items {
+            summary.items = items.len();
+            for item in items {
+                cache.items.insert(item.data.key.clone(), item.data);
+            }
+        }
+
+        if since > 0 && version > since {
+            let deleted = self.get_deleted(since).await?;
+            summary.deleted = deleted
+                .collections
+                .iter()
+                .filter(|key| cache.collections.remove(*key).is_some())
+                .count()
+                + deleted
+                    .items
+                    .iter()
+                    .filter(|key| cache.items.remove(*key).is_some())
+                    .count();
+        }
+
+        cache.version = version;
+        tracing::info!(?summary, "refreshed Zotero cache");
+
+        Ok(summary)
+    }
+
+    /// The library's items and collections from the local cache, refreshed with any changes.
+    ///
+    /// If the library can't be reached, the cache is returned as it is.
+    pub async fn cached_library(&self) -> Result<LibraryCache, ZoteroError> {
+        let path = self.cache_path();
+        let mut cache = LibraryCache::load(&path)?;
+        let since = cache.version;
+
+        match self.refresh_cache(&mut cache).await {
+            Ok(summary) if summary.version == since => {}
+            Ok(_) => cache.save(&path)?,
+            Err(e) if cache.version > 0 => {
+                tracing::warn!(error = %e, "unable to refresh Zotero cache, using cached library");
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(cache)
+    }
+
+    /// The location of the library's cache, e.g. `<cache_dir>/users_12345.json`.
+    pub fn cache_path(&self) -> PathBuf {
+        let mut library: Vec<&str> = self.base_url.rsplit('/').take(2).collect();
+        library.reverse();
+        self.cache_dir.join(format!("{}.json", library.join("_")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::zotero::cache::LibraryCache;
+    use crate::zotero::collection::models::Collection;
+    use crate::zotero::item::models::Item;
+
+    fn item(key: &str, parent: Option<&str>, collections: &[&str]) -> Item {
+        serde_json::from_value(json!({
+            "key": key,
+            "version": 1,
+            "parentItem": parent,
+            "itemType": if parent.is_some() { "attachment" } else { "journalArticle" },
+            "title": key,
+            "collections": collections,
+            "dateAdded": "2024-01-01T00:00:00Z",
+            "dateModified": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_collection_items_include_children() {
+        let mut cache = LibraryCache::default();
+        for item in [
+            item("PAPER1", None, &["CLIMATE"]),
+            item("PDF1", Some("PAPER1"), &[]),
+            item("PAPER2", None, &["COVID"]),
+            item("PDF2", Some("PAPER2"), &[]),
+        ] {
+            cache.items.insert(item.key.clone(), item);
+        }
+
+        let mut keys: Vec<String> = cache
+            .collection_items("CLIMATE")
+            .into_iter()
+            .map(|item| item.key)
+            .collect();
+        keys.sort();
+
+        assert_eq!(keys, vec!["PAPER1", "PDF1"]);
+    }
+
+    #[test]
+    fn test_collection_items_recursive() {
+        let mut cache = LibraryCache::default();
+        for (key, parent) in [("CLIMATE", None), ("PALEO", Some("CLIMATE"))] {
+            cache.collections.insert(
+                key.to_string(),
+                Collection {
+                    key: key.to_string(),
+                    name: key.to_lowercase(),
+                    parent_collection: parent.map(str::to_string),
+                    num_collections: 0,
+                    num_items: 1,
+                },
+            );
+        }
+        for item in [
+            item("PAPER1", None, &["CLIMATE"]),
+            item("PAPER2", None, &["PALEO"]),
+            item("PDF2", Some("PAPER2"), &[]),
+        ] {
+            cache.items.insert(item.key.clone(), item);
+        }
+
+        assert_eq!(cache.collection_items("CLIMATE").len(), 1);
+        assert_eq!(cache.collection_items_recursive("CLIMATE").len(), 3);
+    }
+}
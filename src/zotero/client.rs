@@ -4,37 +4,114 @@
 //!
 //! The client is a simple wrapper around the reqwest client, with a few convenience methods for
 //! making requests to the Zotero API.
+//!
+//! Every request honours the API's `Backoff` and `Retry-After` headers: see
+//! [`Throttle`](crate::zotero::throttle::Throttle).
+
+use std::path::PathBuf;
 
-use reqwest::header::HeaderMap;
+use futures::{stream, StreamExt};
+use reqwest::header::{HeaderMap, LINK};
+use reqwest::{RequestBuilder, Response, StatusCode};
 use serde::de::DeserializeOwned;
+use uuid::Uuid;
 
+use crate::anythingllm::retry::RetryPolicy;
 use crate::zotero::error::ZoteroError;
+use crate::zotero::library::Library;
+use crate::zotero::throttle::Throttle;
 
 use super::item::models::ItemUpdateData;
 
+/// The base URL of the Zotero API.
+pub const API_URL: &str = "https://api.zotero.org";
+
+/// The maximum number of results the API returns per request.
+pub const MAX_PAGE_SIZE: usize = 100;
+
+/// The number of pages requested concurrently when the total number of results is known.
+const MAX_CONCURRENT_PAGES: usize = 4;
+
+/// The number of times a write is attempted when the object has been modified since it was read.
+pub const MAX_VERSION_ATTEMPTS: u32 = 3;
+
+// The version of a single object, such as an item
+#[derive(serde::Deserialize)]
+struct VersionResponse {
+    version: i64,
+}
+
+// One page of results from a multi-object request, with the total number of results, the URL of
+// the next page and the library version, if the response reports them
+struct Page<T> {
+    results: Vec<T>,
+    total: Option<usize>,
+    next: Option<String>,
+    version: Option<i64>,
+}
+
 /// A client for the Zotero API
+///
+/// Requests are made to the library the client was created for, at `base_url`.
 #[derive(Debug)]
 pub struct ZoteroClient {
     pub base_url: String,
+    /// The ID of the user the API key belongs to.
+    pub user_id: String,
+    /// The library the client works with.
+    pub library: Library,
     pub client: reqwest::Client,
+    /// The number of results requested per page of a multi-object request.
+    pub page_size: usize,
+    /// The policy used to retry throttled requests.
+    pub retry_policy: RetryPolicy,
+    /// The pause requested by the API, shared by all requests.
+    pub throttle: Throttle,
+    /// The directory holding the local cache of the library.
+    pub cache_dir: PathBuf,
 }
 
 impl ZoteroClient {
     pub fn new(api_key: &str, user_id: &str) -> Self {
+        Self::for_library(api_key, user_id, &Library::User)
+    }
+
+    /// Create a client for `library`, which may be the user's own library or a group library.
+    pub fn for_library(api_key: &str, user_id: &str, library: &Library) -> Self {
         let headers = [("Zotero-API-Key", api_key), ("Zotero-API-Version", "3")]
             .iter()
             .map(|(k, v)| (k.parse().unwrap(), v.parse().unwrap()))
             .collect::<HeaderMap>();
 
         Self {
-            base_url: format!("https://api.zotero.org/users/{}", user_id),
+            base_url: format!("{}/{}", API_URL, library.path(user_id)),
+            user_id: user_id.to_string(),
+            library: library.clone(),
             client: reqwest::Client::builder()
                 .default_headers(headers)
                 .build()
                 .unwrap(),
+            page_size: MAX_PAGE_SIZE,
+            retry_policy: RetryPolicy::default(),
+            throttle: Throttle::new(),
+            cache_dir: Self::default_cache_dir(),
         }
     }
 
+    /// The default location of library caches.
+    pub fn default_cache_dir() -> PathBuf {
+        let dirs = directories_next::ProjectDirs::from("com", "richardlyon", "aza").unwrap();
+        dirs.data_dir().join("zotero")
+    }
+
+    /// The base URL of the API, i.e. `base_url` without the library path.
+    pub fn api_url(&self) -> &str {
+        self.base_url
+            .rsplitn(3, '/')
+            .last()
+            .unwrap_or(&self.base_url)
+    }
+
     pub async fn get(
         &self,
         endpoint: &str,
@@ -42,7 +119,7 @@ impl ZoteroClient {
     ) -> Result<reqwest::Response, reqwest::Error> {
         let url = format!("{}/{}", self.base_url, endpoint);
         let params = params.unwrap_or_default();
-        self.client.get(&url).query(&params).send().await
+        self.send(|| self.client.get(&url).query(&params)).await
     }
 
     /// Get an endpoint and deserialize
@@ -54,10 +131,7 @@ impl ZoteroClient {
         let url = format!("{}/{}", self.base_url, endpoint);
         let params = params.unwrap_or_default(); // eta-reduction
         let response = self
-            .client
-            .get(&url)
-            .query(&params)
-            .send()
+            .send(|| self.client.get(&url).query(&params))
             .await?
             .error_for_status()?;
         let data = response.json::<T>().await?;
@@ -65,25 +139,325 @@ impl ZoteroClient {
         Ok::<T, ZoteroError>(data)
     }
 
-    /// Patch an endpoint
+    /// Get every result of a multi-object request, such as `items` or `collections`.
+    ///
+    /// The first page reports the number of results in its `Total-Results` header, and the
+    /// remaining pages are then requested concurrently. If the header is missing, the `Link`
+    /// header's `rel="next"` URL is followed until there are no more pages.
+    pub async fn get_paginated<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        params: Option<Vec<(&str, &str)>>,
+    ) -> Result<Vec<T>, ZoteroError> {
+        let url = format!("{}/{}", self.base_url, endpoint);
+        self.get_paginated_url(&url, &params.unwrap_or_default())
+            .await
+    }
+
+    /// Get every result of a multi-object request at `url`, which may be outside the library.
+    pub async fn get_paginated_url<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: &[(&str, &str)],
+    ) -> Result<Vec<T>, ZoteroError> {
+        let (results, _) = self
+            .get_all_pages(url, params, None)
+            .await?
+            .unwrap_or_default();
+
+        Ok(results)
+    }
+
+    /// Get every result of a multi-object request that has changed since library `version`, with
+    /// the library version the results reflect.
+    ///
+    /// Returns `None` if nothing has changed since `version` (`304 Not Modified`).
+    pub async fn get_modified_since<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        params: Option<Vec<(&str, &str)>>,
+        version: i64,
+    ) -> Result<Option<(Vec<T>, i64)>, ZoteroError> {
+        let since = version.to_string();
+        let mut params = params.unwrap_or_default();
+        params.push(("since", &since));
+
+        let url = format!("{}/{}", self.base_url, endpoint);
+        let pages = self.get_all_pages(&url, &params, Some(version)).await?;
+
+        Ok(pages.map(|(results, last_modified)| (results, last_modified.unwrap_or(version))))
+    }
+
+    // Get every page of results, and the `Last-Modified-Version` of the first. If
+    // `if_modified_since` is given and nothing has changed since that version, return `None`.
+    async fn get_all_pages<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: &[(&str, &str)],
+        if_modified_since: Option<i64>,
+    ) -> Result<Option<(Vec<T>, Option<i64>)>, ZoteroError> {
+        let Some(first) = self
+            .get_page::<T>(url, params, 0, if_modified_since)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let mut results = first.results;
+        let mut next = first.next;
+
+        match first.total {
+            // the API may return fewer results per page than requested, so step by the size of
+            // the first page
+            Some(total) if !results.is_empty() => {
+                let starts = (results.len()..total).step_by(results.len());
+                let pages: Vec<Result<Vec<T>, ZoteroError>> = stream::iter(starts)
+                    .map(|start| async move {
+                        let page = self.get_page::<T>(url, params, start, None).await?;
+                        Ok(page.map(|page| page.results).unwrap_or_default())
+                    })
+                    .buffered(MAX_CONCURRENT_PAGES)
+                    .collect()
+                    .await;
+
+                for page in pages {
+                    results.extend(page?);
+                }
+            }
+            Some(_) => {}
+            None => {
+                while let Some(url) = next {
+                    tracing::debug!(url, "following next page");
+                    let response = self
+                        .send(|| self.client.get(&url))
+                        .await?
+                        .error_for_status()?;
+                    next = Self::next_link(response.headers());
+                    results.extend(response.json::<Vec<T>>().await?);
+                }
+            }
+        }
+
+        Ok(Some((results, first.version)))
+    }
+
+    // Get one page of results starting at `start`. If `if_modified_since` is given and nothing
+    // has changed since that version, return `None`.
+    async fn get_page<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        params: &[(&str, &str)],
+        start: usize,
+        if_modified_since: Option<i64>,
+    ) -> Result<Option<Page<T>>, ZoteroError> {
+        let limit = self.page_size.to_string();
+        let start = start.to_string();
+        let mut params = params.to_vec();
+        params.push(("limit", &limit));
+        params.push(("start", &start));
+
+        let response = self
+            .send(|| {
+                let request = self.client.get(url).query(&params);
+                match if_modified_since {
+                    Some(version) => request.header("If-Modified-Since-Version", version),
+                    None => request,
+                }
+            })
+            .await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        let response = response.error_for_status()?;
+
+        let total = response
+            .headers()
+            .get("Total-Results")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+        let next = Self::next_link(response.headers());
+        let version = Self::last_modified_version(response.headers());
+        let results = response.json::<Vec<T>>().await?;
+
+        Ok(Some(Page {
+            results,
+            total,
+            next,
+            version,
+        }))
+    }
+
+    /// The library version in a `Last-Modified-Version` header.
+    pub fn last_modified_version(headers: &HeaderMap) -> Option<i64> {
+        headers
+            .get("Last-Modified-Version")?
+            .to_str()
+            .ok()?
+            .parse::<i64>()
+            .ok()
+    }
+
+    // The URL of the next page from a `Link` header such as
+    // `<https://api.zotero.org/users/1/items?start=100>; rel="next", <...>; rel="last"`
+    fn next_link(headers: &HeaderMap) -> Option<String> {
+        headers
+            .get(LINK)?
+            .to_str()
+            .ok()?
+            .split(',')
+            .find(|link| link.contains("rel=\"next\""))?
+            .split(';')
+            .next()
+            .map(|url| url.trim().trim_start_matches('<').trim_end_matches('>'))
+            .map(str::to_string)
+    }
+
+    /// Patch an endpoint, such as `items/<item_key>`, with `data`
+    ///
+    /// `version` is the version of the object `data` was based on. If the object has been modified
+    /// since (`412 Precondition Failed`), the object is fetched again and `data` applied to its
+    /// newer version, up to `MAX_VERSION_ATTEMPTS` times in all. Returns the object's new version.
     pub async fn patch(
         &self,
         endpoint: &str,
         version: i64,
         data: &ItemUpdateData,
-    ) -> Result<(), ZoteroError> {
+    ) -> Result<i64, ZoteroError> {
         let url = format!("{}/{}", self.base_url, endpoint);
         let json_data = serde_json::to_value(data).unwrap();
+        let mut version = version;
+        let mut attempt = 1;
+
+        loop {
+            let response = self
+                .send(|| {
+                    self.client
+                        .patch(&url)
+                        .header("If-Unmodified-Since-Version", version)
+                        .header(reqwest::header::CONTENT_TYPE, "application/json")
+                        .json(&json_data)
+                })
+                .await?;
+
+            match Self::check_status(response).await {
+                Ok(response) => {
+                    return Ok(Self::last_modified_version(response.headers()).unwrap_or(version));
+                }
+                Err(ZoteroError::VersionConflict(_)) if attempt < MAX_VERSION_ATTEMPTS => {
+                    let current = self.get_version(endpoint).await?;
+                    tracing::warn!(
+                        endpoint,
+                        version,
+                        current,
+                        "Zotero object modified, retrying"
+                    );
+                    version = current;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // The current version of the object at `endpoint`
+    async fn get_version(&self, endpoint: &str) -> Result<i64, ZoteroError> {
+        let url = format!("{}/{}", self.base_url, endpoint);
+        let response = self.send(|| self.client.get(&url)).await?;
+        let response = Self::check_status(response).await?;
+        let object = response.json::<VersionResponse>().await?;
 
-        let _ = self
-            .client
-            .patch(&url)
-            .header("If-Unmodified-Since-Version", version)
-            .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .json(&json_data)
-            .send()
-            .await;
+        Ok(object.version)
+    }
+
+    // Return the response if it succeeded, or else the error for its status
+    async fn check_status(response: Response) -> Result<Response, ZoteroError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let url = response.url().to_string();
+        let message = match response.text().await {
+            Ok(text) if !text.trim().is_empty() => format!("{}: {}", url, text.trim()),
+            _ => url,
+        };
+
+        Err(match status {
+            StatusCode::PRECONDITION_FAILED => ZoteroError::VersionConflict(message),
+            StatusCode::FORBIDDEN => ZoteroError::Forbidden(message),
+            StatusCode::NOT_FOUND => ZoteroError::NotFound(message),
+            _ => ZoteroError::ApiError {
+                status: status.as_u16(),
+                message,
+            },
+        })
+    }
+
+    /// Post JSON to an endpoint, and deserialize the response
+    ///
+    /// The request carries a `Zotero-Write-Token`, so that the API won't create objects twice if
+    /// the request is retried.
+    pub async fn post<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        data: &serde_json::Value,
+    ) -> Result<T, ZoteroError> {
+        let url = format!("{}/{}", self.base_url, endpoint);
+        let write_token = Uuid::new_v4().simple().to_string();
 
-        Ok(())
+        let response = self
+            .send(|| {
+                self.client
+                    .post(&url)
+                    .header("Zotero-Write-Token", &write_token)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .json(data)
+            })
+            .await?;
+        let response = Self::check_status(response).await?;
+        let data = response.json::<T>().await?;
+
+        Ok(data)
+    }
+
+    // Send the request built by `make_request` once any pause requested by the API has passed.
+    //
+    // A `Backoff` header pauses every request made through the client. Throttled responses are
+    // retried after their `Retry-After` delay, or the retry policy's backoff if there is none, up
+    // to the policy's maximum number of attempts. The final response is returned whatever its
+    // status.
+    async fn send<F>(&self, make_request: F) -> Result<Response, reqwest::Error>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let policy = &self.retry_policy;
+        let mut attempt = 1;
+
+        loop {
+            self.throttle.wait().await;
+            let response = make_request().send().await?;
+
+            if let Some(delay) = Throttle::backoff(response.headers()) {
+                if self.throttle.pause(delay) {
+                    tracing::warn!(url = %response.url(), ?delay, "Zotero API requested backoff");
+                }
+            }
+
+            if !Throttle::is_throttled(response.status()) || attempt >= policy.max_attempts {
+                return Ok(response);
+            }
+
+            let delay =
+                RetryPolicy::retry_after(&response).unwrap_or_else(|| policy.backoff(attempt));
+            tracing::warn!(
+                url = %response.url(),
+                status = %response.status(),
+                attempt,
+                ?delay,
+                "Zotero API request throttled, retrying"
+            );
+            self.throttle.pause(delay);
+            attempt += 1;
+        }
     }
 }
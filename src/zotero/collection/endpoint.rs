@@ -1,5 +1,5 @@
 use crate::zotero::client::ZoteroClient;
-use crate::zotero::collection::models::{Collection, CollectionsResponse};
+use crate::zotero::collection::models::{Collection, CollectionNode, CollectionsResponse};
 use crate::zotero::error::ZoteroError;
 
 impl ZoteroClient {
@@ -10,10 +10,13 @@ impl ZoteroClient {
         params: Option<Vec<(&str, &str)>>,
     ) -> Result<Vec<Collection>, ZoteroError> {
         let response = self
-            .get_deserialized::<Vec<CollectionsResponse>>("collections", params)
+            .get_paginated::<CollectionsResponse>("collections", params)
             .await?;
 
-        let collections: Vec<Collection> = response.iter().map(|c| c.data.clone()).collect();
+        let collections: Vec<Collection> = response
+            .into_iter()
+            .map(CollectionsResponse::collection)
+            .collect();
 
         Ok(collections)
     }
@@ -31,6 +34,12 @@ impl ZoteroClient {
                 params,
             )
             .await?;
-        Ok(response.data.clone())
+        Ok(response.collection())
+    }
+
+    /// All collections in the library, arranged as trees of their subcollections
+    pub async fn get_collection_tree(&self) -> Result<Vec<CollectionNode>, ZoteroError> {
+        let collections = self.get_collections(None).await?;
+        Ok(Collection::tree(&collections))
     }
 }
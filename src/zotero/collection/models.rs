@@ -1,12 +1,261 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::zotero::error::ZoteroError;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CollectionsResponse {
     pub data: Collection,
+    #[serde(default)]
+    pub meta: CollectionMeta,
+}
+
+impl CollectionsResponse {
+    /// The collection, with its counts from `meta`.
+    pub fn collection(self) -> Collection {
+        Collection {
+            num_collections: self.meta.num_collections,
+            num_items: self.meta.num_items,
+            ..self.data
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CollectionMeta {
+    #[serde(rename = "numCollections", default)]
+    pub num_collections: u64,
+    #[serde(rename = "numItems", default)]
+    pub num_items: u64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Collection {
     pub key: String,
     pub name: String,
+    /// The key of the parent collection, if this is a subcollection.
+    #[serde(
+        rename = "parentCollection",
+        default,
+        deserialize_with = "deserialize_parent_collection"
+    )]
+    pub parent_collection: Option<String>,
+    /// The number of subcollections directly in the collection.
+    #[serde(rename = "numCollections", default)]
+    pub num_collections: u64,
+    /// The number of top-level items directly in the collection.
+    #[serde(rename = "numItems", default)]
+    pub num_items: u64,
+}
+
+/// A collection and its subcollections.
+#[derive(Debug, Clone)]
+pub struct CollectionNode {
+    pub collection: Collection,
+    pub children: Vec<CollectionNode>,
+}
+
+impl Collection {
+    /// Return the collection in `collections` named `collection_name`, if there is exactly one.
+    ///
+    /// A name may be a path of collection names separated by `/`, such as `climate/paleo`, to pick
+    /// out a subcollection whose name isn't unique. The path needn't start at a top-level
+    /// collection.
+    /// NOTE: Case insensitive so 'COVID' matches 'covid'
+    pub fn find_by_name(
+        collections: &[Collection],
+        collection_name: &str,
+    ) -> Result<Collection, ZoteroError> {
+        let mut matching_collections: Vec<_> = collections
+            .iter()
+            .filter(|w| w.name.to_lowercase() == collection_name.to_lowercase())
+            .collect();
+
+        if matching_collections.is_empty() && collection_name.contains('/') {
+            let path: Vec<String> = collection_name
+                .split('/')
+                .map(|name| name.trim().to_lowercase())
+                .collect();
+            matching_collections = collections
+                .iter()
+                .filter(|c| Self::path_ends_with(collections, c, &path))
+                .collect();
+        }
+
+        match matching_collections.len() {
+            0 => Err(ZoteroError::CustomError(format!(
+                "No collection with name {} found",
+                collection_name
+            ))),
+            1 => Ok(matching_collections[0].clone()),
+            _ => Err(ZoteroError::CustomError(format!(
+                "Multiple collections with name {} found",
+                collection_name
+            ))),
+        }
+    }
+
+    /// The path of a collection from its top-level collection, e.g. `climate/paleo`.
+    pub fn path(collections: &[Collection], collection: &Collection) -> String {
+        let mut names = vec![collection.name.clone()];
+        let mut parent = collection.parent_collection.as_deref();
+        while let Some(key) = parent {
+            match collections.iter().find(|c| c.key == key) {
+                // stop at a cycle rather than loop forever
+                Some(c) if names.len() <= collections.len() => {
+                    names.push(c.name.clone());
+                    parent = c.parent_collection.as_deref();
+                }
+                _ => break,
+            }
+        }
+        names.reverse();
+        names.join("/")
+    }
+
+    /// The keys of a collection and all of its subcollections, however deeply nested.
+    pub fn descendant_keys(collections: &[Collection], collection_key: &str) -> Vec<String> {
+        let mut keys = vec![collection_key.to_string()];
+        let mut i = 0;
+        while i < keys.len() {
+            let children = collections
+                .iter()
+                .filter(|c| c.parent_collection.as_deref() == Some(keys[i].as_str()))
+                .filter(|c| !keys.contains(&c.key))
+                .map(|c| c.key.clone())
+                .collect::<Vec<_>>();
+            keys.extend(children);
+            i += 1;
+        }
+        keys
+    }
+
+    /// Arrange collections into trees of their subcollections, sorted by name.
+    pub fn tree(collections: &[Collection]) -> Vec<CollectionNode> {
+        // collections whose parent is missing are treated as top-level
+        let is_root = |c: &Collection| match &c.parent_collection {
+            Some(parent) => !collections.iter().any(|p| &p.key == parent),
+            None => true,
+        };
+        let mut roots: Vec<CollectionNode> = collections
+            .iter()
+            .filter(|c| is_root(c))
+            .map(|c| Self::node(collections, c, &mut vec![c.key.clone()]))
+            .collect();
+        roots.sort_by_key(|n| n.collection.name.to_lowercase());
+        roots
+    }
+
+    // helper functions ///////////////////////////////////////////////////////////////////////
+
+    // Build the tree under `collection`, skipping any collection already on the path to it
+    fn node(
+        collections: &[Collection],
+        collection: &Collection,
+        visited: &mut Vec<String>,
+    ) -> CollectionNode {
+        let mut children = Vec::new();
+        for child in collections
+            .iter()
+            .filter(|c| c.parent_collection.as_deref() == Some(collection.key.as_str()))
+        {
+            if visited.contains(&child.key) {
+                continue;
+            }
+            visited.push(child.key.clone());
+            children.push(Self::node(collections, child, visited));
+            visited.pop();
+        }
+        children.sort_by_key(|n| n.collection.name.to_lowercase());
+
+        CollectionNode {
+            collection: collection.clone(),
+            children,
+        }
+    }
+
+    // Return true if the names of `collection` and its ancestors end with `path`
+    fn path_ends_with(
+        collections: &[Collection],
+        collection: &Collection,
+        path: &[String],
+    ) -> bool {
+        let mut current = Some(collection);
+        for name in path.iter().rev() {
+            match current {
+                Some(c) if &c.name.to_lowercase() == name => {
+                    current = c
+                        .parent_collection
+                        .as_deref()
+                        .and_then(|key| collections.iter().find(|p| p.key == key));
+                }
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+// The API gives `parentCollection` as the parent's key, or `false` for a top-level collection
+fn deserialize_parent_collection<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<serde_json::Value>::deserialize(deserializer)? {
+        Some(serde_json::Value::String(key)) => Ok(Some(key)),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::zotero::collection::models::Collection;
+
+    fn collection(key: &str, name: &str, parent: Option<&str>) -> Collection {
+        Collection {
+            key: key.to_string(),
+            name: name.to_string(),
+            parent_collection: parent.map(str::to_string),
+            num_collections: 0,
+            num_items: 0,
+        }
+    }
+
+    fn collections() -> Vec<Collection> {
+        vec![
+            collection("CLIMATE", "climate", None),
+            collection("PALEO", "paleo", Some("CLIMATE")),
+            collection("ICE", "ice cores", Some("PALEO")),
+            collection("OCEANS", "oceans", None),
+            collection("OPALEO", "paleo", Some("OCEANS")),
+        ]
+    }
+
+    #[test]
+    fn test_find_by_path() {
+        let collections = collections();
+
+        assert!(Collection::find_by_name(&collections, "paleo").is_err());
+        let paleo = Collection::find_by_name(&collections, "Climate/Paleo").unwrap();
+        assert_eq!(paleo.key, "PALEO");
+        let ice = Collection::find_by_name(&collections, "paleo/ice cores").unwrap();
+        assert_eq!(
+            Collection::path(&collections, &ice),
+            "climate/paleo/ice cores"
+        );
+    }
+
+    #[test]
+    fn test_tree_and_descendants() {
+        let collections = collections();
+
+        let tree = Collection::tree(&collections);
+        let roots: Vec<&str> = tree.iter().map(|n| n.collection.key.as_str()).collect();
+        assert_eq!(roots, vec!["CLIMATE", "OCEANS"]);
+        assert_eq!(tree[0].children[0].children[0].collection.key, "ICE");
+
+        assert_eq!(
+            Collection::descendant_keys(&collections, "CLIMATE"),
+            vec!["CLIMATE", "PALEO", "ICE"]
+        );
+    }
 }
@@ -20,6 +20,20 @@ pub enum ZoteroError {
     PDFPathError(String),
     #[error("Deserialisation error")]
     DeserializationError,
+    #[error("Cache error: {0}")]
+    CacheError(String),
+    #[error("Zotero database error: {0}")]
+    DatabaseError(String),
+    #[error("Version conflict, object modified since it was read: {0}")]
+    VersionConflict(String),
+    #[error("Forbidden, check the API key's permissions: {0}")]
+    Forbidden(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Zotero API error ({status}): {message}")]
+    ApiError { status: u16, message: String },
+    #[error("Zotero rejected the write ({code}): {message}")]
+    WriteFailed { code: u16, message: String },
 
     #[error("Custom error: {0}")]
     CustomError(String),
@@ -31,6 +45,12 @@ impl From<reqwest::Error> for ZoteroError {
     }
 }
 
+impl From<rusqlite::Error> for ZoteroError {
+    fn from(error: rusqlite::Error) -> ZoteroError {
+        ZoteroError::DatabaseError(error.to_string())
+    }
+}
+
 impl From<LLMError> for ZoteroError {
     fn from(error: LLMError) -> Self {
         ZoteroError::UnhandledError(error.to_string())
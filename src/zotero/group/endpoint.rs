@@ -0,0 +1,12 @@
+use crate::zotero::client::ZoteroClient;
+use crate::zotero::error::ZoteroError;
+use crate::zotero::group::models::GroupsResponse;
+
+impl ZoteroClient {
+    /// GET /users/<user_id>/groups
+    /// The groups the user belongs to, whichever library the client works with
+    pub async fn get_groups(&self) -> Result<Vec<GroupsResponse>, ZoteroError> {
+        let url = format!("{}/users/{}/groups", self.api_url(), self.user_id);
+        self.get_paginated_url::<GroupsResponse>(&url, &[]).await
+    }
+}
@@ -0,0 +1,5 @@
+//! `Group` models and endpoints for the `zotero` client.
+//!
+
+pub mod endpoint;
+pub mod models;
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GroupsResponse {
+    pub id: u64,
+    pub data: Group,
+    #[serde(default)]
+    pub meta: GroupMeta,
+}
+
+/// A group the user belongs to
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Group {
+    pub id: u64,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub group_type: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GroupMeta {
+    #[serde(rename = "numItems")]
+    pub num_items: Option<u64>,
+}
@@ -1,13 +1,22 @@
+use std::collections::BTreeMap;
+
 use futures::StreamExt;
+use serde::Serialize;
 
-use crate::zotero::client::ZoteroClient;
+use crate::zotero::client::{ZoteroClient, MAX_VERSION_ATTEMPTS};
 use crate::zotero::collection::models::Collection;
 use crate::zotero::error::ZoteroError;
-use crate::zotero::item::models::{Item, ItemUpdateData, ItemsResponse};
+use crate::zotero::item::models::{
+    Item, ItemUpdate, ItemUpdateData, ItemWriteResult, ItemsResponse, NewNote, Tag, WriteResponse,
+    WriteResult,
+};
+
+/// The maximum number of objects the API accepts in one write request.
+pub const MAX_WRITE_OBJECTS: usize = 50;
 
 impl ZoteroClient {
     /// GET /items
-    pub fn get_items(&self) -> impl futures::stream::Stream<Item = Item> + '_ {
+    pub fn get_items(&self) -> impl futures::stream::Stream<Item = Result<Item, ZoteroError>> + '_ {
         self.get_batched("items".to_string())
     }
 
@@ -17,7 +26,7 @@ impl ZoteroClient {
     pub fn get_collections_collection_key_items_batched(
         &self,
         collection_key: String,
-    ) -> impl futures::stream::Stream<Item = Item> + '_ {
+    ) -> impl futures::stream::Stream<Item = Result<Item, ZoteroError>> + '_ {
         let endpoint = format!("collections/{}/items", collection_key);
         self.get_batched(endpoint)
     }
@@ -58,30 +67,156 @@ impl ZoteroClient {
         Ok(())
     }
 
-    /// Get all items in the library in batches
-    fn get_batched(&self, endpoint: String) -> impl futures::stream::Stream<Item = Item> + '_ {
-        const MAX_RESULTS: i32 = 2000;
-        const CHUNK_SIZE: i32 = 100;
-        let chunks = MAX_RESULTS / CHUNK_SIZE;
-
-        futures::stream::iter((0..chunks).map(move |x| {
-            let endpoint = endpoint.clone();
-            ZoteroClient::process_batch(endpoint, x * CHUNK_SIZE, CHUNK_SIZE, self)
-        }))
-        .buffer_unordered(chunks as usize)
-        .map(|f| {
-            if let Err(e) = &f {
-                // FIXME improve the error return type to aid identifying deserialisation error
-                panic!("{}", e);
+    /// POST /items
+    ///
+    /// Create or update up to 50 items, and report the result for each
+    pub async fn post_items<T: Serialize>(
+        &self,
+        items: &[T],
+    ) -> Result<WriteResponse, ZoteroError> {
+        let data = serde_json::to_value(items).unwrap();
+        self.post::<WriteResponse>("items", &data).await
+    }
+
+    /// GET /items?itemKey=<item_keys>&format=versions
+    ///
+    /// Get the current versions of up to 50 items
+    pub async fn get_item_versions(
+        &self,
+        item_keys: &[&str],
+    ) -> Result<BTreeMap<String, i64>, ZoteroError> {
+        let item_keys = item_keys.join(",");
+        self.get_deserialized::<BTreeMap<String, i64>>(
+            "items",
+            Some(vec![("itemKey", &item_keys), ("format", "versions")]),
+        )
+        .await
+    }
+
+    /// Update items in batches of up to `MAX_WRITE_OBJECTS`, and return the result for each
+    ///
+    /// Updates to the same item are merged into one (see `ItemUpdate::group`), and a result is
+    /// returned for each item. If an item has been modified since the version an update was based on,
+    /// its current version is fetched and the update applied to that, up to `MAX_VERSION_ATTEMPTS`
+    /// times in all.
+    pub async fn update_items_batch(&self, updates: Vec<ItemUpdate>) -> Vec<ItemWriteResult> {
+        let updates = ItemUpdate::group(updates);
+        let mut results = Vec::with_capacity(updates.len());
+        for batch in updates.chunks(MAX_WRITE_OBJECTS) {
+            results.extend(self.update_items(batch.to_vec()).await);
+        }
+        results
+    }
+
+    /// Add a note to the parent of an item, and return the note's key
+    pub async fn create_child_note(
+        &self,
+        item: &Item,
+        note: String,
+        tags: Vec<Tag>,
+    ) -> Result<String, ZoteroError> {
+        let parent_key = match &item.parent_item {
+            Some(parent_key) => parent_key,
+            None => return Err(ZoteroError::CustomError("Item has no parent".to_string())),
+        };
+        let response = self
+            .post_items(&[NewNote::new(parent_key, note, tags)])
+            .await?;
+
+        if let Some(failure) = response.failed.get("0") {
+            return Err(ZoteroError::WriteFailed {
+                code: failure.code,
+                message: failure.message.clone(),
+            });
+        }
+        response
+            .success
+            .get("0")
+            .cloned()
+            .ok_or(ZoteroError::DeserializationError)
+    }
+
+    // Write one batch of updates, retrying those whose item has been modified since
+    async fn update_items(&self, mut batch: Vec<ItemUpdate>) -> Vec<ItemWriteResult> {
+        let mut results = vec![None; batch.len()];
+        let mut pending: Vec<usize> = (0..batch.len()).collect();
+        let mut attempt = 1;
+
+        loop {
+            let updates: Vec<&ItemUpdate> = pending.iter().map(|&i| &batch[i]).collect();
+            let outcome = match self.post_items(&updates).await {
+                Ok(response) => response.results(updates.len()),
+                Err(e) => vec![
+                    WriteResult::Failed {
+                        code: None,
+                        message: e.to_string(),
+                    };
+                    updates.len()
+                ],
+            };
+
+            let mut conflicts = Vec::new();
+            for (&i, result) in pending.iter().zip(outcome) {
+                if result.is_version_conflict() && attempt < MAX_VERSION_ATTEMPTS {
+                    conflicts.push(i);
+                }
+                results[i] = Some(result);
             }
-            f.ok()
-        })
-        .flat_map(futures::stream::iter)
-        .flat_map(futures::stream::iter)
+            if conflicts.is_empty() {
+                break;
+            }
+
+            let keys: Vec<&str> = conflicts.iter().map(|&i| batch[i].key.as_str()).collect();
+            match self.get_item_versions(&keys).await {
+                Ok(versions) => {
+                    tracing::warn!(?versions, "Zotero items modified, retrying");
+                    for &i in &conflicts {
+                        if let Some(version) = versions.get(&batch[i].key) {
+                            batch[i].version = *version;
+                        }
+                    }
+                }
+                // the conflicts stand as the items' results
+                Err(e) => {
+                    tracing::warn!(error = %e, "unable to get versions of modified Zotero items");
+                    break;
+                }
+            }
+            pending = conflicts;
+            attempt += 1;
+        }
+
+        batch
+            .into_iter()
+            .zip(results)
+            .map(|(update, result)| ItemWriteResult {
+                key: update.key,
+                result: result.unwrap_or(WriteResult::Failed {
+                    code: None,
+                    message: "not written".to_string(),
+                }),
+            })
+            .collect()
     }
 
-    // Ok(s) -> S<Ok[1], Ok[2]>
-    // Err(e) -> S<Err[e]>
+    /// Get all items from an endpoint, however many pages they span
+    ///
+    /// If the items can't be fetched or deserialized, the stream yields the error and ends.
+    fn get_batched(
+        &self,
+        endpoint: String,
+    ) -> impl futures::stream::Stream<Item = Result<Item, ZoteroError>> + '_ {
+        futures::stream::once(
+            async move { self.get_paginated::<ItemsResponse>(&endpoint, None).await },
+        )
+        .flat_map(|result| {
+            let items: Vec<Result<Item, ZoteroError>> = match result {
+                Ok(items) => items.into_iter().map(|item| Ok(item.data)).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            futures::stream::iter(items)
+        })
+    }
 
     /// Return a matching collection if collection_name corresponds to exactly one workspace
     /// NOTE: Case insensitive so 'COVID' matches 'covid'
@@ -91,37 +226,6 @@ impl ZoteroClient {
         collection_name: &str,
     ) -> Result<Collection, ZoteroError> {
         let collections = self.get_collections(None).await?;
-        let matching_collections: Vec<_> = collections
-            .iter()
-            .filter(|w| w.name.to_lowercase() == collection_name.to_lowercase())
-            .collect();
-
-        match matching_collections.len() {
-            0 => Err(ZoteroError::CustomError(format!(
-                "No collection with name {} found",
-                collection_name
-            ))),
-            1 => Ok(matching_collections[0].clone()),
-            _ => Err(ZoteroError::CustomError(format!(
-                "Multiple collections with name {} found",
-                collection_name
-            ))),
-        }
-    }
-
-    #[tracing::instrument]
-    async fn process_batch(
-        endpoint: String,
-        offset: i32,
-        limit: i32,
-        client: &ZoteroClient,
-    ) -> Result<Vec<Item>, ZoteroError> {
-        let limit = limit.to_string();
-        let offset = offset.to_string();
-        let params = vec![("limit", limit.as_str()), ("start", offset.as_str())];
-        let response = client.get(&endpoint, Some(params)).await?;
-        let items_response = response.json::<Vec<ItemsResponse>>().await?;
-
-        Ok(items_response.iter().map(|c| c.data.clone()).collect())
+        Collection::find_by_name(&collections, collection_name)
     }
 }
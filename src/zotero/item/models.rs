@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use chrono::{DateTime, Utc};
@@ -18,6 +19,11 @@ pub struct Item {
     #[serde(rename = "itemType")]
     pub item_type: ItemType,
     pub title: String,
+    pub url: Option<String>,
+    /// The keys of the collections the item is in. Child items, such as attachments, are in the
+    /// collections of their parent.
+    #[serde(default)]
+    pub collections: Vec<String>,
     pub creators: Option<Vec<Creator>>,
     #[serde(rename = "abstractNote")]
     pub abstract_note: Option<String>,
@@ -36,6 +42,12 @@ impl Item {
         self.content_type.as_deref() == Some("application/pdf")
     }
 
+    /// Return true if the item is a web page with a URL.
+    pub fn is_webpage(&self) -> bool {
+        matches!(self.item_type, ItemType::WebPage)
+            && self.url.as_deref().is_some_and(|u| !u.is_empty())
+    }
+
     pub fn filepath(&self, root: &std::path::Path) -> Option<PathBuf> {
         self.filename
             .as_ref()
@@ -125,6 +137,175 @@ pub struct ItemUpdateData {
     pub creators: Option<Vec<Creator>>,
 }
 
+impl ItemUpdateData {
+    /// Combine two updates to an item.
+    ///
+    /// The tags of both are kept. An item has one abstract, title and list of creators, so those
+    /// set in `self` are kept over those in `other`.
+    pub fn merge(self, other: ItemUpdateData) -> Self {
+        let tags = match (self.tags, other.tags) {
+            (Some(mut tags), Some(other_tags)) => {
+                for tag in other_tags {
+                    if !tags.iter().any(|t| t.tag == tag.tag) {
+                        tags.push(tag);
+                    }
+                }
+                Some(tags)
+            }
+            (tags, other_tags) => tags.or(other_tags),
+        };
+
+        Self {
+            abstract_note: self.abstract_note.or(other.abstract_note),
+            tags,
+            title: self.title.or(other.title),
+            creators: self.creators.or(other.creators),
+        }
+    }
+}
+
+/// An update to an item, based on version `version` of the item.
+#[derive(Serialize, Clone, Debug)]
+pub struct ItemUpdate {
+    pub key: String,
+    pub version: i64,
+    #[serde(flatten)]
+    pub data: ItemUpdateData,
+}
+
+impl ItemUpdate {
+    /// Merge the updates to each item into one, in the order first seen.
+    ///
+    /// The merged update is based on the newest version of the item any of them was based on, so
+    /// that one item is never written twice in a request.
+    pub fn group(updates: Vec<ItemUpdate>) -> Vec<ItemUpdate> {
+        let mut grouped: Vec<ItemUpdate> = Vec::new();
+        for update in updates {
+            match grouped.iter_mut().find(|g| g.key == update.key) {
+                Some(group) => {
+                    group.version = group.version.max(update.version);
+                    group.data = std::mem::take(&mut group.data).merge(update.data);
+                }
+                None => grouped.push(update),
+            }
+        }
+        grouped
+    }
+}
+
+/// A new note, as a child of the item `parent_item`.
+#[derive(Serialize, Debug, Clone)]
+pub struct NewNote {
+    #[serde(rename = "itemType")]
+    pub item_type: ItemType,
+    #[serde(rename = "parentItem")]
+    pub parent_item: String,
+    /// The note's content, as HTML.
+    pub note: String,
+    pub tags: Vec<Tag>,
+}
+
+impl NewNote {
+    pub fn new(parent_item: &str, note: String, tags: Vec<Tag>) -> Self {
+        Self {
+            item_type: ItemType::Note,
+            parent_item: parent_item.to_string(),
+            note,
+            tags,
+        }
+    }
+}
+
+/// Structure to deserialize the response from the API to a write request.
+///
+/// Each map is keyed by the index of the object in the request. `successful` holds the objects as
+/// saved, and `success` their keys.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WriteResponse {
+    #[serde(default)]
+    pub successful: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub success: BTreeMap<String, String>,
+    #[serde(default)]
+    pub unchanged: BTreeMap<String, String>,
+    #[serde(default)]
+    pub failed: BTreeMap<String, WriteFailure>,
+}
+
+impl WriteResponse {
+    /// The result for each of the `count` objects in the request, in order.
+    pub fn results(&self, count: usize) -> Vec<WriteResult> {
+        (0..count)
+            .map(|index| {
+                let index = index.to_string();
+                if let Some(object) = self.successful.get(&index) {
+                    WriteResult::Successful {
+                        version: object.get("version").and_then(serde_json::Value::as_i64),
+                    }
+                } else if self.success.contains_key(&index) {
+                    WriteResult::Successful { version: None }
+                } else if self.unchanged.contains_key(&index) {
+                    WriteResult::Unchanged
+                } else if let Some(failure) = self.failed.get(&index) {
+                    WriteResult::Failed {
+                        code: Some(failure.code),
+                        message: failure.message.clone(),
+                    }
+                } else {
+                    WriteResult::Failed {
+                        code: None,
+                        message: "missing from the response".to_string(),
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// The result of writing one object of a multi-object write.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteResult {
+    /// The object was saved, and is now at `version` if the API reported it.
+    Successful { version: Option<i64> },
+    /// The object was already as written.
+    Unchanged,
+    /// The object wasn't saved, with the API's error code if it gave one.
+    Failed { code: Option<u16>, message: String },
+}
+
+impl WriteResult {
+    /// Return true if the object was saved or was already as written.
+    pub fn is_ok(&self) -> bool {
+        !matches!(self, WriteResult::Failed { .. })
+    }
+
+    /// Return true if the object wasn't saved because it had been modified since it was read.
+    pub fn is_version_conflict(&self) -> bool {
+        matches!(
+            self,
+            WriteResult::Failed {
+                code: Some(412),
+                ..
+            }
+        )
+    }
+}
+
+/// The result of writing an update to item `key`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemWriteResult {
+    pub key: String,
+    pub result: WriteResult,
+}
+
+/// Why the API didn't save an object.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WriteFailure {
+    pub key: Option<String>,
+    pub code: u16,
+    pub message: String,
+}
+
 fn deserialize_utc_date<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
 where
     D: Deserializer<'de>,
@@ -135,3 +316,79 @@ where
         Err(_) => Err(serde::de::Error::custom("Invalid date format")),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::zotero::item::models::{
+        ItemUpdate, ItemUpdateData, Tag, WriteResponse, WriteResult,
+    };
+
+    fn update(key: &str, version: i64, data: ItemUpdateData) -> ItemUpdate {
+        ItemUpdate {
+            key: key.to_string(),
+            version,
+            data,
+        }
+    }
+
+    #[test]
+    fn test_group_updates_by_key() {
+        let data = |abstract_note: &str, tags: &[&str]| ItemUpdateData {
+            abstract_note: Some(abstract_note.to_string()),
+            tags: Some(
+                tags.iter()
+                    .map(|tag| Tag {
+                        tag: tag.to_string(),
+                    })
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+
+        let grouped = ItemUpdate::group(vec![
+            update("PAPER001", 1, data("First abstract", &["ice", "ayda"])),
+            update("PAPER002", 1, data("An abstract", &[])),
+            update("PAPER001", 2, data("Second abstract", &["ocean", "ayda"])),
+        ]);
+
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped[0].key, "PAPER001");
+        assert_eq!(grouped[0].version, 2);
+        assert_eq!(
+            grouped[0].data.abstract_note.as_deref(),
+            Some("First abstract")
+        );
+        let tags: Vec<&str> = grouped[0]
+            .data
+            .tags
+            .iter()
+            .flatten()
+            .map(|t| t.tag.as_str())
+            .collect();
+        assert_eq!(tags, vec!["ice", "ayda", "ocean"]);
+
+        let json = serde_json::to_value(&grouped[1]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"key": "PAPER002", "version": 1, "abstractNote": "An abstract", "tags": []})
+        );
+    }
+
+    #[test]
+    fn test_write_response_results() {
+        let response: WriteResponse = serde_json::from_value(serde_json::json!({
+            "successful": {"0": {"key": "PAPER001", "version": 5}},
+            "success": {"0": "PAPER001"},
+            "unchanged": {"2": "PAPER003"},
+            "failed": {"1": {"key": "PAPER002", "code": 412, "message": "Item has been modified"}},
+        }))
+        .unwrap();
+
+        let results = response.results(4);
+
+        assert_eq!(results[0], WriteResult::Successful { version: Some(5) });
+        assert!(results[1].is_version_conflict());
+        assert_eq!(results[2], WriteResult::Unchanged);
+        assert!(!results[3].is_ok());
+    }
+}
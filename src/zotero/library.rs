@@ -0,0 +1,96 @@
+//! The Zotero library a client works with.
+//!
+//! A library is either the user's own library or a group library the user belongs to. On the
+//! command line and in the configuration file it is written as `user` or `group:<GROUP_ID>`.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// A Zotero library.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Library {
+    /// The library of the user the API key belongs to.
+    #[default]
+    User,
+    /// A group library.
+    Group(u64),
+}
+
+impl Library {
+    /// Return true if this is the user's own library.
+    pub fn is_user(&self) -> bool {
+        matches!(self, Library::User)
+    }
+
+    /// The path of the library in the API, e.g. `users/12345` or `groups/67890`.
+    pub fn path(&self, user_id: &str) -> String {
+        match self {
+            Library::User => format!("users/{}", user_id),
+            Library::Group(group_id) => format!("groups/{}", group_id),
+        }
+    }
+}
+
+impl FromStr for Library {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("user") {
+            return Ok(Library::User);
+        }
+
+        let group_id = s.strip_prefix("group:").unwrap_or(s);
+        group_id.parse::<u64>().map(Library::Group).map_err(|_| {
+            format!(
+                "invalid library '{}': expected 'user' or 'group:<GROUP_ID>'",
+                s
+            )
+        })
+    }
+}
+
+impl TryFrom<String> for Library {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Library> for String {
+    fn from(library: Library) -> Self {
+        library.to_string()
+    }
+}
+
+impl Display for Library {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Library::User => write!(f, "user"),
+            Library::Group(group_id) => write!(f, "group:{}", group_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::zotero::library::Library;
+
+    #[test]
+    fn test_parse_library() {
+        assert_eq!("user".parse::<Library>(), Ok(Library::User));
+        assert_eq!("group:1234".parse::<Library>(), Ok(Library::Group(1234)));
+        assert_eq!("1234".parse::<Library>(), Ok(Library::Group(1234)));
+        assert!("group:abc".parse::<Library>().is_err());
+    }
+
+    #[test]
+    fn test_library_path() {
+        assert_eq!(Library::User.path("42"), "users/42");
+        assert_eq!(Library::Group(1234).path("42"), "groups/1234");
+    }
+}
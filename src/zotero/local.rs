@@ -0,0 +1,324 @@
+//! A read-only Zotero source that reads the Zotero desktop app's database.
+//!
+//! The desktop app keeps its library in `zotero.sqlite` in the Zotero data directory, next to the
+//! `storage` directory of attachment files. The app holds an exclusive lock on the database while
+//! it runs, so the database is copied to a temporary directory and the copy is read. Nothing is
+//! ever written.
+//!
+//! Items and collections are returned as the same models as the web API, so commands work the same
+//! with either source. Notes, annotations and items in the trash are skipped.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OpenFlags};
+use serde_json::{json, Map, Value};
+
+use crate::zotero::cache::{LibraryCache, CACHE_FORMAT};
+use crate::zotero::collection::models::Collection;
+use crate::zotero::error::ZoteroError;
+use crate::zotero::item::models::Item;
+use crate::zotero::library::Library;
+use crate::zotero::source::ZoteroSource;
+
+/// The name of the database in the Zotero data directory.
+pub const DATABASE_NAME: &str = "zotero.sqlite";
+
+/// The local Zotero database at `database`.
+#[derive(Debug, Clone)]
+pub struct LocalZotero {
+    pub database: PathBuf,
+    pub library: Library,
+}
+
+impl LocalZotero {
+    pub fn new(database: PathBuf, library: Library) -> Self {
+        Self { database, library }
+    }
+
+    /// Read the library's items and collections from a copy of the database.
+    pub fn read(&self) -> Result<LibraryCache, ZoteroError> {
+        let dir = tempfile::tempdir().map_err(|e| ZoteroError::DatabaseError(e.to_string()))?;
+        let copy = self.copy_database(dir.path())?;
+        let connection = Connection::open_with_flags(&copy, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+        let (library_id, version) = self.library_id(&connection)?;
+        let collections = read_collections(&connection, library_id)?;
+        let items = read_items(&connection, library_id)?;
+
+        Ok(LibraryCache {
+            format: CACHE_FORMAT,
+            version,
+            collections: collections
+                .into_iter()
+                .map(|c| (c.key.clone(), c))
+                .collect(),
+            items: items.into_iter().map(|i| (i.key.clone(), i)).collect(),
+        })
+    }
+
+    // helper functions ///////////////////////////////////////////////////////////////////////
+
+    // Copy the database, and its write-ahead log if there is one, into `dir`
+    fn copy_database(&self, dir: &Path) -> Result<PathBuf, ZoteroError> {
+        if !self.database.is_file() {
+            return Err(ZoteroError::DatabaseError(format!(
+                "no Zotero database at {}",
+                self.database.display()
+            )));
+        }
+
+        let copy = dir.join(DATABASE_NAME);
+        std::fs::copy(&self.database, &copy)
+            .map_err(|e| ZoteroError::DatabaseError(e.to_string()))?;
+
+        let wal = self.database.with_extension("sqlite-wal");
+        if wal.is_file() {
+            std::fs::copy(&wal, copy.with_extension("sqlite-wal"))
+                .map_err(|e| ZoteroError::DatabaseError(e.to_string()))?;
+        }
+
+        Ok(copy)
+    }
+
+    // The database's ID of the library, and the library's version
+    fn library_id(&self, connection: &Connection) -> Result<(i64, i64), ZoteroError> {
+        let result = match &self.library {
+            Library::User => connection.query_row(
+                "SELECT libraryID, version FROM libraries WHERE type = 'user'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            ),
+            Library::Group(group_id) => connection.query_row(
+                "SELECT l.libraryID, l.version FROM groups g
+                 JOIN libraries l ON l.libraryID = g.libraryID
+                 WHERE g.groupID = ?1",
+                params![*group_id as i64],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            ),
+        };
+
+        match result {
+            Ok(library) => Ok(library),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Err(ZoteroError::DatabaseError(format!(
+                "no {} library in the Zotero database",
+                self.library
+            ))),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl ZoteroSource for LocalZotero {
+    fn library(&self) -> &Library {
+        &self.library
+    }
+
+    async fn read_library(&self) -> Result<LibraryCache, ZoteroError> {
+        let local = self.clone();
+        tokio::task::spawn_blocking(move || local.read())
+            .await
+            .map_err(|e| ZoteroError::DatabaseError(e.to_string()))?
+    }
+}
+
+fn read_collections(
+    connection: &Connection,
+    library_id: i64,
+) -> Result<Vec<Collection>, ZoteroError> {
+    let mut statement = connection.prepare(
+        "SELECT c.key, c.collectionName, p.key,
+            (SELECT COUNT(*) FROM collections s WHERE s.parentCollectionID = c.collectionID),
+            (SELECT COUNT(*) FROM collectionItems ci
+             WHERE ci.collectionID = c.collectionID
+             AND ci.itemID NOT IN (SELECT itemID FROM deletedItems))
+         FROM collections c
+         LEFT JOIN collections p ON p.collectionID = c.parentCollectionID
+         WHERE c.libraryID = ?1",
+    )?;
+
+    let collections = statement
+        .query_map(params![library_id], |row| {
+            Ok(Collection {
+                key: row.get(0)?,
+                name: row.get(1)?,
+                parent_collection: row.get(2)?,
+                num_collections: row.get(3)?,
+                num_items: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(collections)
+}
+
+// Items are assembled as the JSON the web API returns for them, and deserialized into the same
+// model, so that both sources agree on names and formats
+fn read_items(connection: &Connection, library_id: i64) -> Result<Vec<Item>, ZoteroError> {
+    let mut items: BTreeMap<i64, Map<String, Value>> = BTreeMap::new();
+
+    let mut statement = connection.prepare(
+        "SELECT i.itemID, i.key, i.version, t.typeName, i.dateAdded, i.dateModified
+         FROM items i
+         JOIN itemTypes t ON t.itemTypeID = i.itemTypeID
+         WHERE i.libraryID = ?1
+         AND t.typeName NOT IN ('note', 'annotation')
+         AND i.itemID NOT IN (SELECT itemID FROM deletedItems)",
+    )?;
+    let mut rows = statement.query(params![library_id])?;
+    while let Some(row) = rows.next()? {
+        let item = json!({
+            "key": row.get::<_, String>(1)?,
+            "version": row.get::<_, i64>(2)?,
+            "itemType": row.get::<_, String>(3)?,
+            "title": "",
+            "dateAdded": utc_date(&row.get::<_, String>(4)?),
+            "dateModified": utc_date(&row.get::<_, String>(5)?),
+            "collections": [],
+            "creators": [],
+            "tags": [],
+        });
+        if let Value::Object(item) = item {
+            items.insert(row.get(0)?, item);
+        }
+    }
+
+    // fields such as title, url and abstractNote
+    for_each_row(
+        connection,
+        "SELECT d.itemID, f.fieldName, v.value
+         FROM itemData d
+         JOIN fields f ON f.fieldID = d.fieldID
+         JOIN itemDataValues v ON v.valueID = d.valueID",
+        |row| {
+            if let Some(item) = items.get_mut(&row.get(0)?) {
+                let value: rusqlite::types::Value = row.get(2)?;
+                let value = match value {
+                    rusqlite::types::Value::Text(text) => Value::String(text),
+                    rusqlite::types::Value::Integer(n) => Value::String(n.to_string()),
+                    rusqlite::types::Value::Real(n) => Value::String(n.to_string()),
+                    _ => return Ok(()),
+                };
+                item.insert(row.get(1)?, value);
+            }
+            Ok(())
+        },
+    )?;
+
+    // attachments, whose stored files are at `storage/<key>/<filename>`
+    for_each_row(
+        connection,
+        "SELECT a.itemID, p.key, a.contentType, a.path
+         FROM itemAttachments a
+         LEFT JOIN items p ON p.itemID = a.parentItemID",
+        |row| {
+            if let Some(item) = items.get_mut(&row.get(0)?) {
+                item.insert("parentItem".into(), json!(row.get::<_, Option<String>>(1)?));
+                item.insert(
+                    "contentType".into(),
+                    json!(row.get::<_, Option<String>>(2)?),
+                );
+                let path: Option<String> = row.get(3)?;
+                if let Some(filename) = path.as_deref().and_then(|p| p.strip_prefix("storage:")) {
+                    item.insert("filename".into(), json!(filename));
+                }
+            }
+            Ok(())
+        },
+    )?;
+
+    for_each_row(
+        connection,
+        "SELECT ic.itemID, c.firstName, c.lastName, t.creatorType
+         FROM itemCreators ic
+         JOIN creators c ON c.creatorID = ic.creatorID
+         JOIN creatorTypes t ON t.creatorTypeID = ic.creatorTypeID
+         ORDER BY ic.itemID, ic.orderIndex",
+        |row| {
+            if let Some(Value::Array(creators)) = items
+                .get_mut(&row.get(0)?)
+                .and_then(|i| i.get_mut("creators"))
+            {
+                creators.push(json!({
+                    "firstName": row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    "lastName": row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    "creatorType": row.get::<_, String>(3)?,
+                }));
+            }
+            Ok(())
+        },
+    )?;
+
+    for_each_row(
+        connection,
+        "SELECT it.itemID, t.name FROM itemTags it JOIN tags t ON t.tagID = it.tagID",
+        |row| {
+            if let Some(Value::Array(tags)) =
+                items.get_mut(&row.get(0)?).and_then(|i| i.get_mut("tags"))
+            {
+                tags.push(json!({ "tag": row.get::<_, String>(1)? }));
+            }
+            Ok(())
+        },
+    )?;
+
+    for_each_row(
+        connection,
+        "SELECT ci.itemID, c.key
+         FROM collectionItems ci
+         JOIN collections c ON c.collectionID = ci.collectionID",
+        |row| {
+            if let Some(Value::Array(collections)) = items
+                .get_mut(&row.get(0)?)
+                .and_then(|i| i.get_mut("collections"))
+            {
+                collections.push(json!(row.get::<_, String>(1)?));
+            }
+            Ok(())
+        },
+    )?;
+
+    let items = items
+        .into_values()
+        .filter_map(
+            |item| match serde_json::from_value::<Item>(Value::Object(item.clone())) {
+                Ok(item) => Some(item),
+                Err(e) => {
+                    tracing::warn!(key = ?item.get("key"), error = %e, "skipping Zotero item");
+                    None
+                }
+            },
+        )
+        .collect();
+
+    Ok(items)
+}
+
+// Run `sql` and call `f` with each row
+fn for_each_row<F>(connection: &Connection, sql: &str, mut f: F) -> Result<(), ZoteroError>
+where
+    F: FnMut(&rusqlite::Row) -> rusqlite::Result<()>,
+{
+    let mut statement = connection.prepare(sql)?;
+    let mut rows = statement.query([])?;
+    while let Some(row) = rows.next()? {
+        f(row)?;
+    }
+    Ok(())
+}
+
+// The database stores UTC times as `2024-03-10 21:48:42`, and the API as `2024-03-10T21:48:42Z`
+fn utc_date(date: &str) -> String {
+    format!("{}Z", date.trim().replacen(' ', "T", 1))
+}
+
+/// The default location of the database: the Zotero data directory that holds the `storage`
+/// directory of attachment files at `storage_path`.
+pub fn default_database_path(storage_path: &Path) -> PathBuf {
+    storage_path
+        .parent()
+        .unwrap_or(storage_path)
+        .join(DATABASE_NAME)
+}
@@ -1,7 +1,13 @@
 //! Zotero client library.
 //!
 
+pub mod cache;
 pub mod client;
 pub mod collection;
-pub(crate) mod error;
+pub mod error;
+pub mod group;
 pub mod item;
+pub mod library;
+pub mod local;
+pub mod source;
+pub mod throttle;
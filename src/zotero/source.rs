@@ -0,0 +1,46 @@
+//! Sources of Zotero items and collections.
+//!
+//! Commands that only read a library, such as listing collections or importing them, are written
+//! against the `ZoteroSource` trait, so that they work with either the Zotero web API or the
+//! database of the Zotero desktop app on this machine.
+
+use async_trait::async_trait;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::zotero::cache::LibraryCache;
+use crate::zotero::client::ZoteroClient;
+use crate::zotero::error::ZoteroError;
+use crate::zotero::library::Library;
+
+/// The available sources of Zotero libraries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ZoteroSourceType {
+    /// The Zotero web API
+    #[default]
+    Web,
+    /// The database of the Zotero desktop app, read offline
+    Local,
+}
+
+/// A read-only view of a Zotero library.
+#[async_trait]
+pub trait ZoteroSource: Send + Sync {
+    /// The library being read.
+    fn library(&self) -> &Library;
+
+    /// The library's items and collections.
+    async fn read_library(&self) -> Result<LibraryCache, ZoteroError>;
+}
+
+#[async_trait]
+impl ZoteroSource for ZoteroClient {
+    fn library(&self) -> &Library {
+        &self.library
+    }
+
+    async fn read_library(&self) -> Result<LibraryCache, ZoteroError> {
+        self.cached_library().await
+    }
+}
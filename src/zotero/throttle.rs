@@ -0,0 +1,110 @@
+//! Throttling of requests to the Zotero API.
+//!
+//! Under load the API asks clients to slow down: a `Backoff` header on any response asks for no
+//! further requests for that many seconds, and `429 Too Many Requests` or `503 Service
+//! Unavailable` responses may carry a `Retry-After` header. The wait applies to the client as a
+//! whole, so every concurrent request pauses until it has passed.
+//!
+//! see: [Rate limiting](https://www.zotero.org/support/dev/web_api/v3/basics#rate_limiting)
+
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+/// A pause shared by every request made through a client.
+#[derive(Debug)]
+pub struct Throttle {
+    until: watch::Sender<Option<Instant>>,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Throttle {
+    pub fn new() -> Self {
+        let (until, _) = watch::channel(None);
+        Self { until }
+    }
+
+    /// Pause all requests for `delay`.
+    ///
+    /// A pause never shortens one that is already in place. Returns true if the pause was extended.
+    pub fn pause(&self, delay: Duration) -> bool {
+        let until = Instant::now() + delay;
+        self.until.send_if_modified(|current| match current {
+            Some(current) if *current >= until => false,
+            _ => {
+                *current = Some(until);
+                true
+            }
+        })
+    }
+
+    /// Wait until any pause has passed.
+    pub async fn wait(&self) {
+        // the pause may be extended while waiting, so check again after each sleep
+        loop {
+            let until = *self.until.borrow();
+            match until {
+                Some(until) if until > Instant::now() => tokio::time::sleep_until(until).await,
+                _ => return,
+            }
+        }
+    }
+
+    /// Subscribe to the end of the current pause, e.g. to show it in a progress bar.
+    pub fn subscribe(&self) -> watch::Receiver<Option<Instant>> {
+        self.until.subscribe()
+    }
+
+    /// The delay requested by a `Backoff` header, in seconds.
+    pub fn backoff(headers: &HeaderMap) -> Option<Duration> {
+        let seconds = headers
+            .get("Backoff")?
+            .to_str()
+            .ok()?
+            .trim()
+            .parse::<u64>()
+            .ok()?;
+        Some(Duration::from_secs(seconds))
+    }
+
+    /// Return true if the response status means the request was throttled and should be retried.
+    pub fn is_throttled(status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::header::HeaderMap;
+
+    use crate::zotero::throttle::Throttle;
+
+    #[test]
+    fn test_backoff_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(Throttle::backoff(&headers), None);
+
+        headers.insert("Backoff", "30".parse().unwrap());
+        assert_eq!(Throttle::backoff(&headers), Some(Duration::from_secs(30)));
+    }
+
+    #[tokio::test]
+    async fn test_pause_is_never_shortened() {
+        let throttle = Throttle::new();
+        assert!(throttle.pause(Duration::from_secs(60)));
+        assert!(!throttle.pause(Duration::from_secs(1)));
+
+        let until = throttle.subscribe().borrow().unwrap();
+        assert!(until - tokio::time::Instant::now() > Duration::from_secs(30));
+    }
+}
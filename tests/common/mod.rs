@@ -1,7 +1,26 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
 use ayda::anythingllm::client::AnythingLLMClient;
-use ayda::anythingllm::workspace::Workspace;
+use ayda::anythingllm::error::LLMError;
+use ayda::anythingllm::workspace::{
+    ChatMode, PostWorkspaceSlugChatResponse, StreamChatChunk, Workspace, WorkspaceDocument,
+};
+use ayda::anythingllm::Document;
+use ayda::backend::RetrievalBackend;
+use ayda::zotero::cache::LibraryCache;
 use ayda::zotero::client::ZoteroClient;
+use ayda::zotero::error::ZoteroError;
+use ayda::zotero::library::Library;
+use ayda::zotero::source::ZoteroSource;
 use ayda::Config;
+use chrono::Utc;
+use futures::stream::BoxStream;
+use futures::StreamExt;
 
 pub struct AnythingLLMFixture {
     pub client: AnythingLLMClient,
@@ -10,16 +29,17 @@ pub struct AnythingLLMFixture {
 impl AnythingLLMFixture {
     pub fn new() -> Self {
         let config = Config::from_file().unwrap();
-        let client = AnythingLLMClient::new(
-            &config.anythingllm_ip,
-            &config.anythingllm_port,
+        let client = AnythingLLMClient::with_options(
+            &config.anythingllm_base_url(),
             &config.anythingllm_api_key,
-        );
+            &config.anythingllm_options(),
+        )
+        .unwrap();
 
         Self { client }
     }
 
-    pub async fn with_fixture<F, Fut>(mut self, func: F)
+    pub async fn with_fixture<F, Fut>(self, func: F)
     where
         F: FnOnce(Workspace, AnythingLLMClient) -> Fut,
         Fut: Future<Output = ()>,
@@ -40,6 +60,13 @@ impl AnythingLLMFixture {
 
 use std::future::Future;
 
+/// Create an AnythingLLM client that talks to a mock server.
+pub fn mock_anythingllm_client(server: &mockito::ServerGuard) -> AnythingLLMClient {
+    let host = server.host_with_port();
+    let (ip, port) = host.split_once(':').unwrap();
+    AnythingLLMClient::new(ip, port, "api_key").unwrap()
+}
+
 pub struct ZoteroFixture {
     pub client: ZoteroClient,
 }
@@ -52,3 +79,190 @@ impl ZoteroFixture {
         Self { client }
     }
 }
+
+/// An in-memory retrieval backend for testing commands without an AnythingLLM server.
+///
+/// Documents whose location is in `rejected` are accepted for embedding but never embedded, and
+/// links in `broken_links` fail to upload.
+#[derive(Default)]
+pub struct MemoryBackend {
+    pub workspaces: Mutex<Vec<Workspace>>,
+    pub documents: Mutex<HashMap<String, Vec<WorkspaceDocument>>>,
+    pub rejected: Vec<String>,
+    pub broken_links: Vec<String>,
+}
+
+impl MemoryBackend {
+    fn workspace(id: u32, name: &str) -> Workspace {
+        Workspace {
+            id,
+            name: name.to_string(),
+            slug: name.to_lowercase().replace(' ', "-"),
+            created_at: Utc::now(),
+            last_updated_at: Utc::now(),
+            similarity_threshold: 0.25,
+            chat_mode: ChatMode::Chat,
+            temperature: None,
+            history_length: None,
+            system_prompt: None,
+            top_n: None,
+            documents: None,
+            threads: None,
+        }
+    }
+
+    fn document(id: u32, location: &str) -> WorkspaceDocument {
+        WorkspaceDocument {
+            id,
+            doc_id: location.to_string(),
+            workspace_id: 0,
+            created_at: Utc::now(),
+            last_updated_at: Utc::now(),
+            docpath: location.to_string(),
+            filename: location.to_string(),
+            pinned: false,
+            metadata: Default::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl RetrievalBackend for MemoryBackend {
+    async fn workspaces(&self) -> Result<Vec<Workspace>, LLMError> {
+        Ok(self.workspaces.lock().unwrap().clone())
+    }
+
+    async fn workspace_by_name(&self, name: &str) -> Result<Workspace, LLMError> {
+        self.workspaces
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|w| w.name == name)
+            .cloned()
+            .ok_or(LLMError::WorkspaceIdError(name.to_string()))
+    }
+
+    async fn create_workspace(&self, name: &str) -> Result<Workspace, LLMError> {
+        let mut workspaces = self.workspaces.lock().unwrap();
+        let workspace = Self::workspace(workspaces.len() as u32, name);
+        workspaces.push(workspace.clone());
+        Ok(workspace)
+    }
+
+    async fn delete_workspace(&self, slug: &str) -> Result<(), LLMError> {
+        self.workspaces.lock().unwrap().retain(|w| w.slug != slug);
+        self.documents.lock().unwrap().remove(slug);
+        Ok(())
+    }
+
+    async fn upload_document(&self, path: &Path) -> Result<Document, LLMError> {
+        Ok(Document {
+            doc_id: path.display().to_string(),
+            name: None,
+            title: None,
+            cached: None,
+            location: Some(format!("custom-documents/{}.json", path.display())),
+        })
+    }
+
+    async fn upload_link(&self, link: &str) -> Result<Document, LLMError> {
+        if self.broken_links.iter().any(|l| l == link) {
+            return Err(LLMError::ServiceError("Could not scrape link".to_string()));
+        }
+        self.upload_document(Path::new(link)).await
+    }
+
+    async fn embed_documents(&self, slug: &str, locations: Vec<String>) -> Result<(), LLMError> {
+        let mut documents = self.documents.lock().unwrap();
+        let embedded = documents.entry(slug.to_string()).or_default();
+        for location in locations {
+            if !self.rejected.contains(&location) {
+                embedded.push(Self::document(embedded.len() as u32, &location));
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_documents(&self, locations: Vec<String>) -> Result<(), LLMError> {
+        for embedded in self.documents.lock().unwrap().values_mut() {
+            embedded.retain(|d| !locations.contains(&d.docpath));
+        }
+        Ok(())
+    }
+
+    async fn workspace_documents(&self, slug: &str) -> Result<Vec<WorkspaceDocument>, LLMError> {
+        Ok(self
+            .documents
+            .lock()
+            .unwrap()
+            .get(slug)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn chat(
+        &self,
+        _slug: &str,
+        _thread_slug: Option<&str>,
+        message: &str,
+        _mode: &ChatMode,
+    ) -> Result<PostWorkspaceSlugChatResponse, LLMError> {
+        Ok(PostWorkspaceSlugChatResponse {
+            id: None,
+            close: true,
+            error: None,
+            sources: vec![],
+            text_response: message.to_string(),
+            response_type: "textResponse".to_string(),
+            chat_id: None,
+        })
+    }
+
+    async fn stream_chat<'a>(
+        &'a self,
+        _slug: &'a str,
+        _thread_slug: Option<&'a str>,
+        message: &'a str,
+        _mode: &'a ChatMode,
+    ) -> Result<BoxStream<'a, Result<StreamChatChunk, LLMError>>, LLMError> {
+        let chunks = vec![
+            Ok(StreamChatChunk::Text(message.to_string())),
+            Ok(StreamChatChunk::Close),
+        ];
+        Ok(futures::stream::iter(chunks).boxed())
+    }
+}
+
+/// Create a Zotero client that talks to a mock server.
+/// A Zotero source for testing commands, that returns `cache` as the user's library.
+#[derive(Default)]
+pub struct MemoryZotero {
+    pub library: Library,
+    pub cache: LibraryCache,
+}
+
+#[async_trait]
+impl ZoteroSource for MemoryZotero {
+    fn library(&self) -> &Library {
+        &self.library
+    }
+
+    async fn read_library(&self) -> Result<LibraryCache, ZoteroError> {
+        Ok(self.cache.clone())
+    }
+}
+
+pub fn mock_zotero_client(server: &mockito::ServerGuard) -> ZoteroClient {
+    let mut client = ZoteroClient::new("api_key", "user");
+    client.base_url = format!("{}/users/user", server.url());
+    client
+}
+
+/// Create a Zotero desktop database in `dir` from the fixture `tests/responses/Zotero/zotero.sql`.
+pub fn zotero_database(dir: &Path) -> std::path::PathBuf {
+    let path = dir.join("zotero.sqlite");
+    let sql = std::fs::read_to_string("tests/responses/Zotero/zotero.sql").unwrap();
+    let connection = rusqlite::Connection::open(&path).unwrap();
+    connection.execute_batch(&sql).unwrap();
+    path
+}
@@ -0,0 +1,83 @@
+-- A small Zotero desktop database, using the tables and columns of the real schema that are read.
+--
+-- The user's library holds the collections climate > paleo, with a paper and its PDF in each, a
+-- web page, a note, a paper in the trash and a linked PDF file. A group library holds one paper.
+
+CREATE TABLE libraries (libraryID INTEGER PRIMARY KEY, type TEXT NOT NULL, editable INT NOT NULL DEFAULT 1, filesEditable INT NOT NULL DEFAULT 1, version INT NOT NULL DEFAULT 0, storageVersion INT NOT NULL DEFAULT 0, lastSync INT NOT NULL DEFAULT 0, archived INT NOT NULL DEFAULT 0);
+CREATE TABLE groups (groupID INTEGER PRIMARY KEY, libraryID INT NOT NULL UNIQUE, name TEXT NOT NULL, description TEXT NOT NULL, version INT NOT NULL);
+CREATE TABLE itemTypes (itemTypeID INTEGER PRIMARY KEY, typeName TEXT, templateItemTypeID INT, display INT DEFAULT 1);
+CREATE TABLE fields (fieldID INTEGER PRIMARY KEY, fieldName TEXT, fieldFormatID INT);
+CREATE TABLE items (itemID INTEGER PRIMARY KEY, itemTypeID INT NOT NULL, dateAdded TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP, dateModified TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP, clientDateModified TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP, libraryID INT NOT NULL, key TEXT NOT NULL, version INT NOT NULL DEFAULT 0, synced INT NOT NULL DEFAULT 0, UNIQUE (libraryID, key));
+CREATE TABLE itemDataValues (valueID INTEGER PRIMARY KEY, value UNIQUE);
+CREATE TABLE itemData (itemID INT, fieldID INT, valueID, PRIMARY KEY (itemID, fieldID));
+CREATE TABLE itemAttachments (itemID INTEGER PRIMARY KEY, parentItemID INT, linkMode INT, contentType TEXT, charsetID INT, path TEXT, syncState INT DEFAULT 0, storageModTime INT, storageHash TEXT, lastProcessedModificationTime INT);
+CREATE TABLE itemNotes (itemID INTEGER PRIMARY KEY, parentItemID INT, note TEXT, title TEXT);
+CREATE TABLE creatorTypes (creatorTypeID INTEGER PRIMARY KEY, creatorType TEXT);
+CREATE TABLE creators (creatorID INTEGER PRIMARY KEY, firstName TEXT NOT NULL, lastName TEXT NOT NULL, fieldMode INT, UNIQUE (lastName, firstName, fieldMode));
+CREATE TABLE itemCreators (itemID INT NOT NULL, creatorID INT NOT NULL, creatorTypeID INT NOT NULL DEFAULT 1, orderIndex INT NOT NULL DEFAULT 0, PRIMARY KEY (itemID, creatorID, creatorTypeID, orderIndex));
+CREATE TABLE tags (tagID INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE);
+CREATE TABLE itemTags (itemID INT NOT NULL, tagID INT NOT NULL, type INT NOT NULL, PRIMARY KEY (itemID, tagID));
+CREATE TABLE collections (collectionID INTEGER PRIMARY KEY, collectionName TEXT NOT NULL, parentCollectionID INT DEFAULT NULL, clientDateModified TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP, libraryID INT NOT NULL, key TEXT NOT NULL, version INT NOT NULL DEFAULT 0, synced INT NOT NULL DEFAULT 0, UNIQUE (libraryID, key));
+CREATE TABLE collectionItems (collectionID INT NOT NULL, itemID INT NOT NULL, orderIndex INT NOT NULL DEFAULT 0, PRIMARY KEY (collectionID, itemID));
+CREATE TABLE deletedItems (itemID INTEGER PRIMARY KEY, dateDeleted DEFAULT CURRENT_TIMESTAMP NOT NULL);
+
+INSERT INTO libraries (libraryID, type, version) VALUES (1, 'user', 1234), (2, 'group', 56);
+INSERT INTO groups VALUES (4711, 2, 'Climate Reading Group', '', 56);
+
+INSERT INTO itemTypes (itemTypeID, typeName) VALUES (1, 'annotation'), (2, 'attachment'), (3, 'book'), (22, 'journalArticle'), (28, 'note'), (37, 'webpage');
+INSERT INTO fields (fieldID, fieldName) VALUES (1, 'title'), (2, 'abstractNote'), (13, 'url');
+INSERT INTO creatorTypes VALUES (8, 'author'), (10, 'editor');
+
+INSERT INTO collections (collectionID, collectionName, parentCollectionID, libraryID, key) VALUES
+    (1, 'Climate', NULL, 1, 'CLIMATE1'),
+    (2, 'Paleo', 1, 1, 'PALEO001'),
+    (3, 'Reading', NULL, 2, 'READING1');
+
+INSERT INTO items (itemID, itemTypeID, dateAdded, dateModified, libraryID, key, version) VALUES
+    (1, 22, '2024-03-10 21:48:42', '2024-03-11 08:00:00', 1, 'PAPER001', 100),
+    (2, 2, '2024-03-10 21:48:43', '2024-03-10 21:48:43', 1, 'PDF00001', 101),
+    (3, 22, '2024-03-12 09:00:00', '2024-03-12 09:00:00', 1, 'PAPER002', 102),
+    (4, 2, '2024-03-12 09:00:01', '2024-03-12 09:00:01', 1, 'PDF00002', 103),
+    (5, 37, '2024-03-13 10:00:00', '2024-03-13 10:00:00', 1, 'WEBPAGE1', 104),
+    (6, 28, '2024-03-13 11:00:00', '2024-03-13 11:00:00', 1, 'NOTE0001', 105),
+    (7, 3, '2024-03-14 12:00:00', '2024-03-14 12:00:00', 1, 'TRASHED1', 106),
+    (8, 2, '2024-03-15 13:00:00', '2024-03-15 13:00:00', 1, 'LINKED01', 107),
+    (9, 22, '2024-03-16 14:00:00', '2024-03-16 14:00:00', 2, 'GROUP001', 50),
+    (10, 2, '2024-03-16 14:00:01', '2024-03-16 14:00:01', 2, 'GROUPPDF', 51);
+
+INSERT INTO itemDataValues VALUES
+    (1, 'Ice core records of past climate'),
+    (2, 'A review of ice core records.'),
+    (3, 'Deep ocean temperatures'),
+    (4, 'Climate change explained'),
+    (5, 'https://example.com/climate'),
+    (6, 'A trashed book'),
+    (7, 'Group paper'),
+    (8, 'Full Text PDF');
+INSERT INTO itemData VALUES
+    (1, 1, 1), (1, 2, 2),
+    (2, 1, 8),
+    (3, 1, 3),
+    (4, 1, 8),
+    (5, 1, 4), (5, 13, 5),
+    (7, 1, 6),
+    (8, 1, 8),
+    (9, 1, 7),
+    (10, 1, 8);
+
+INSERT INTO itemAttachments (itemID, parentItemID, linkMode, contentType, path) VALUES
+    (2, 1, 0, 'application/pdf', 'storage:Ice cores.pdf'),
+    (4, 3, 0, 'application/pdf', 'storage:Deep ocean.pdf'),
+    (8, 1, 2, 'application/pdf', '/home/user/papers/linked.pdf'),
+    (10, 9, 0, 'application/pdf', 'storage:Group paper.pdf');
+INSERT INTO itemNotes VALUES (6, NULL, '<p>A note</p>', 'A note');
+
+INSERT INTO creators VALUES (1, 'Jane', 'Smith', 0), (2, 'John', 'Doe', 0);
+INSERT INTO itemCreators VALUES (1, 2, 8, 1), (1, 1, 8, 0), (3, 2, 10, 0);
+
+INSERT INTO tags VALUES (1, 'ice'), (2, 'climate');
+INSERT INTO itemTags VALUES (1, 1, 0), (1, 2, 0);
+
+INSERT INTO collectionItems VALUES (1, 1, 0), (1, 5, 1), (1, 6, 2), (1, 7, 3), (2, 3, 0), (3, 9, 0);
+
+INSERT INTO deletedItems (itemID) VALUES (7);
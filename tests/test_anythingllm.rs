@@ -2,11 +2,15 @@ mod common;
 
 mod tests {
     use std::path::PathBuf;
+    use std::time::Duration;
 
-    use ayda::anythingllm::client::AnythingLLMClient;
+    use ayda::anythingllm::client::{AnythingLLMClient, ClientOptions};
+    use ayda::anythingllm::error::LLMError;
+    use ayda::anythingllm::limiter::Limits;
+    use ayda::anythingllm::retry::RetryPolicy;
     use ayda::Config;
 
-    use crate::common::AnythingLLMFixture;
+    use crate::common::{mock_anythingllm_client, AnythingLLMFixture};
 
     //  // Construction ///////////////////////////////////////////////////////////////////////////////
 
@@ -15,11 +19,134 @@ mod tests {
         let api_key = "api_key";
         let ip = "10.13.10.8";
         let port = "3001";
-        let client = AnythingLLMClient::new(ip, port, api_key);
+        let client = AnythingLLMClient::new(ip, port, api_key).unwrap();
 
         assert_eq!(client.base_url_api_v1, "http://10.13.10.8:3001/api/v1");
     }
 
+    #[test]
+    fn test_client_new_invalid() {
+        assert!(AnythingLLMClient::new("10.13.10.8", "port", "api_key").is_err());
+        assert!(AnythingLLMClient::new("10.13.10.8", "3001", "api\nkey").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_client_with_options_base_url() {
+        let options = ClientOptions {
+            timeout: Some(Duration::from_secs(30)),
+            proxy: Some("http://proxy.example.com:8080".to_string()),
+            ..Default::default()
+        };
+        let client =
+            AnythingLLMClient::with_options("https://example.com/llm/", "api_key", &options)
+                .unwrap();
+
+        assert_eq!(client.base_url, "https://example.com/llm");
+        assert_eq!(client.base_url_api_v1, "https://example.com/llm/api/v1");
+    }
+
+    #[tokio::test]
+    async fn test_client_with_options_invalid() {
+        let options = ClientOptions::default();
+
+        assert!(AnythingLLMClient::with_options("example.com", "api_key", &options).is_err());
+        assert!(AnythingLLMClient::with_options("ftp://example.com", "api_key", &options).is_err());
+
+        let options = ClientOptions {
+            ca_certificates: vec![PathBuf::from("tests/test_data/missing.pem")],
+            ..Default::default()
+        };
+        assert!(
+            AnythingLLMClient::with_options("https://example.com", "api_key", &options).is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_path_prefix_is_used_for_requests() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/llm/api/v1/workspaces")
+            .with_body_from_file("tests/responses/AnythingLLM/get_workspaces.json")
+            .create_async()
+            .await;
+
+        let base_url = format!("{}/llm", server.url());
+        let client =
+            AnythingLLMClient::with_options(&base_url, "api_key", &ClientOptions::default())
+                .unwrap();
+
+        assert!(client.get_workspaces().await.is_ok());
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_config_without_base_url_uses_ip_and_port() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "zotero_user_id": "1",
+                "zotero_api_key": "key",
+                "zotero_library_root_path": "/tmp",
+                "anythingllm_api_key": "key",
+                "anythingllm_ip": "10.13.10.8",
+                "anythingllm_port": "3001"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.anythingllm_base_url(), "http://10.13.10.8:3001");
+    }
+
+    #[test]
+    fn test_config_limits_default_when_partial() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "zotero_user_id": "1",
+                "zotero_api_key": "key",
+                "zotero_library_root_path": "/tmp",
+                "anythingllm_api_key": "key",
+                "anythingllm_base_url": "http://localhost:3001",
+                "anythingllm_limits": { "uploads": 16, "requests_per_second": 5.0 }
+            }"#,
+        )
+        .unwrap();
+
+        let limits = config.anythingllm_options().limits;
+        assert_eq!(limits.uploads, 16);
+        assert_eq!(limits.requests_per_second, Some(5.0));
+        assert_eq!(limits.chats, Limits::default().chats);
+    }
+
+    #[test]
+    fn test_config_retry_policy() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "zotero_user_id": "1",
+                "zotero_api_key": "key",
+                "zotero_library_root_path": "/tmp",
+                "anythingllm_api_key": "key",
+                "anythingllm_base_url": "http://localhost:3001",
+                "anythingllm_retry": { "max_attempts": 6, "max_backoff_ms": 60000 }
+            }"#,
+        )
+        .unwrap();
+
+        let policy = config.anythingllm_options().retry_policy;
+        assert_eq!(policy.max_attempts, 6);
+        assert_eq!(policy.max_backoff, Duration::from_secs(60));
+        assert_eq!(
+            policy.initial_backoff,
+            RetryPolicy::default().initial_backoff
+        );
+    }
+
+    #[test]
+    fn test_client_clones_share_limiter() {
+        let client = AnythingLLMClient::new("127.0.0.1", "3001", "api_key").unwrap();
+        let clone = client.clone();
+
+        assert!(std::sync::Arc::ptr_eq(&client.limiter, &clone.limiter));
+    }
+
     // Authentication /////////////////////////////////////////////////////////////////////////////
 
     #[tokio::test]
@@ -30,7 +157,8 @@ mod tests {
             &config.anythingllm_ip,
             &config.anythingllm_port,
             &config.anythingllm_api_key,
-        );
+        )
+        .unwrap();
 
         assert!(client.get_auth().await.is_ok());
     }
@@ -42,11 +170,298 @@ mod tests {
             &config.anythingllm_ip,
             &config.anythingllm_port,
             "invalid_api_key",
-        );
+        )
+        .unwrap();
 
         assert!(client.get_auth().await.is_err());
     }
 
+    #[tokio::test]
+    async fn test_get_auth_invalid_key_is_auth_error() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/auth")
+            .with_status(403)
+            .with_body(r#"{"error":"Invalid API Key"}"#)
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+
+        assert!(matches!(client.get_auth().await, Err(LLMError::AuthError)));
+    }
+
+    // System /////////////////////////////////////////////////////////////////////////////////////
+
+    #[tokio::test]
+    async fn test_get_system() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/system")
+            .with_body_from_file("tests/responses/AnythingLLM/get_system.json")
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let settings = client.get_system().await.unwrap();
+
+        assert_eq!(settings.llm_provider.as_deref(), Some("lmstudio"));
+        assert_eq!(
+            settings.llm_model().as_deref(),
+            Some("mistral-7b-instruct-v0.2.Q4_K_M.gguf")
+        );
+        assert_eq!(settings.embedding_engine.as_deref(), Some("native"));
+        assert_eq!(settings.vector_db.as_deref(), Some("lancedb"));
+    }
+
+    #[test]
+    fn test_llm_model_of_other_provider() {
+        use ayda::anythingllm::system::SystemSettings;
+
+        let settings: SystemSettings = serde_json::from_value(serde_json::json!({
+            "LLMProvider": "ollama",
+            "OpenAiModelPref": "gpt-3.5-turbo",
+            "LMStudioModelPref": "mistral-7b-instruct-v0.2.Q4_K_M.gguf"
+        }))
+        .unwrap();
+
+        assert_eq!(settings.llm_model(), None);
+    }
+
+    #[tokio::test]
+    async fn test_get_system_vector_count() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/system/vector-count")
+            .with_body(r#"{"vectorCount":1234}"#)
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+
+        assert_eq!(client.get_system_vector_count().await.unwrap(), 1234);
+    }
+
+    #[tokio::test]
+    async fn test_status_report() {
+        use ayda::app::commands::status::status_report;
+
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/auth")
+            .with_body(r#"{"authenticated":true}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/system")
+            .with_body_from_file("tests/responses/AnythingLLM/get_system.json")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/system/vector-count")
+            .with_body(r#"{"vectorCount":1234}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/workspaces")
+            .with_body_from_file("tests/responses/AnythingLLM/get_workspaces.json")
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let report = status_report(&client).await;
+
+        assert!(report.reachable);
+        assert!(report.api_key_valid);
+        assert_eq!(report.llm_provider.as_deref(), Some("lmstudio"));
+        assert_eq!(report.vector_count, Some(1234));
+        assert_eq!(report.workspace_count, Some(2));
+        assert!(report.error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_status_report_invalid_key() {
+        use ayda::app::commands::status::status_report;
+
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/auth")
+            .with_status(403)
+            .with_body(r#"{"error":"Invalid API Key"}"#)
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let report = status_report(&client).await;
+
+        assert!(report.reachable);
+        assert!(!report.api_key_valid);
+        assert!(report.vector_count.is_none());
+    }
+
+    // Retries ////////////////////////////////////////////////////////////////////////////////////
+
+    fn fast_retry_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_retries_server_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let unavailable = server
+            .mock("GET", "/api/v1/workspaces")
+            .with_status(503)
+            .expect(2)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("GET", "/api/v1/workspaces")
+            .with_body_from_file("tests/responses/AnythingLLM/get_workspaces.json")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server).with_retry_policy(fast_retry_policy());
+        let workspaces = client.get_workspaces().await;
+
+        unavailable.assert_async().await;
+        ok.assert_async().await;
+        assert!(workspaces.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_retry_wait_releases_limit() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/workspaces")
+            .with_status(503)
+            .with_header("Retry-After", "1")
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/workspaces")
+            .with_body_from_file("tests/responses/AnythingLLM/get_workspaces.json")
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/auth")
+            .with_body(r#"{"authenticated":true}"#)
+            .create_async()
+            .await;
+
+        let options = ClientOptions {
+            limits: Limits {
+                other: 1,
+                ..Default::default()
+            },
+            retry_policy: RetryPolicy {
+                max_attempts: 2,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_secs(2),
+            },
+            ..Default::default()
+        };
+        let client = AnythingLLMClient::with_options(&server.url(), "api_key", &options).unwrap();
+
+        let retrying = tokio::spawn({
+            let client = client.clone();
+            async move { client.get_workspaces().await }
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // the only slot for other requests is free while the first waits out its Retry-After
+        let auth = tokio::time::timeout(Duration::from_millis(500), client.get_auth()).await;
+        assert!(matches!(auth, Ok(Ok(true))));
+        assert!(retrying.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_get_gives_up_on_long_retry_after() {
+        let mut server = mockito::Server::new_async().await;
+        let unavailable = server
+            .mock("GET", "/api/v1/workspaces")
+            .with_status(503)
+            .with_header("Retry-After", "3600")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server).with_retry_policy(fast_retry_policy());
+        let workspaces = client.get_workspaces().await;
+
+        unavailable.assert_async().await;
+        assert!(workspaces.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_does_not_retry_client_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let not_found = server
+            .mock("GET", "/api/v1/workspaces")
+            .with_status(404)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server).with_retry_policy(fast_retry_policy());
+        let workspaces = client.get_workspaces().await;
+
+        not_found.assert_async().await;
+        assert!(workspaces.is_err());
+    }
+
+    // Errors /////////////////////////////////////////////////////////////////////////////////////
+
+    #[tokio::test]
+    async fn test_error_relays_server_message() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/workspace/missing")
+            .with_status(404)
+            .with_body(r#"{"error":"No workspace found"}"#)
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let result = client.get_workspace_by_slug("missing").await;
+
+        assert!(matches!(
+            result,
+            Err(LLMError::NotFound { ref endpoint, ref message })
+                if endpoint == "workspace/missing" && message == "No workspace found"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_delete_workspace_bad_request_quirk() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/workspace/ws")
+            .with_body_from_file("tests/responses/AnythingLLM/get_workspace.json")
+            .create_async()
+            .await;
+        server
+            .mock("DELETE", "/api/system/remove-documents")
+            .create_async()
+            .await;
+        server
+            .mock("DELETE", "/api/v1/workspace/ws")
+            .with_status(200)
+            .with_body("Bad Request")
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let result = client.delete_workspace_slug("ws").await;
+
+        assert!(matches!(result, Err(LLMError::ValidationError { .. })));
+    }
+
     // Document tests /////////////////////////////////////////////////////////////////////////////
 
     // #[tokio::test]
@@ -119,18 +534,221 @@ mod tests {
         let test_doc_filepath = PathBuf::from("tests/test_data/DELETE ME test document.pdf");
 
         fixture
-            .with_fixture(|w, c| async move {
+            .with_fixture(|_w, c| async move {
                 let doc = c.post_document_upload(&test_doc_filepath).await.unwrap();
 
                 let before_count = c.get_documents().await.unwrap().len();
                 let docs = vec![doc.location.clone().unwrap()];
-                let _ = c.delete_api_system_remove_documents(docs).await.unwrap();
+                c.delete_api_system_remove_documents(docs).await.unwrap();
                 let after_count = c.get_documents().await.unwrap().len();
                 assert_eq!((before_count - after_count), 1);
             })
             .await;
     }
 
+    // Chat tests /////////////////////////////////////////////////////////////////////////////////
+
+    #[tokio::test]
+    async fn test_stream_workspace_slug_chat() {
+        use ayda::anythingllm::{ChatMode, StreamChatChunk};
+        use futures::StreamExt;
+
+        let mut server = mockito::Server::new_async().await;
+        let body = concat!(
+            "data: {\"uuid\":\"1\",\"type\":\"textResponseChunk\",\"textResponse\":\"Hello \",\"sources\":[],\"close\":false,\"error\":false}\n\n",
+            "data: {\"uuid\":\"1\",\"type\":\"textResponseChunk\",\"textResponse\":\"world\",\"sources\":[],\"close\":true,\"error\":false}\n\n",
+        );
+        let mock = server
+            .mock("POST", "/api/v1/workspace/test/stream-chat")
+            .with_header("content-type", "text/event-stream")
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let chunks: Vec<_> = client
+            .stream_workspace_slug_chat("test", "hi", &ChatMode::Chat)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        mock.assert_async().await;
+        let text: String = chunks
+            .iter()
+            .filter_map(|c| match c {
+                Ok(StreamChatChunk::Text(t)) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "Hello world");
+        assert!(matches!(chunks.last(), Some(Ok(StreamChatChunk::Close))));
+    }
+
+    #[tokio::test]
+    async fn test_stream_workspace_slug_chat_unterminated_last_event() {
+        use ayda::anythingllm::{ChatMode, StreamChatChunk};
+        use futures::StreamExt;
+
+        let mut server = mockito::Server::new_async().await;
+        let body = concat!(
+            "data: {\"uuid\":\"1\",\"type\":\"textResponseChunk\",\"textResponse\":\"Hello \",\"sources\":[],\"close\":false,\"error\":false}\n\n",
+            "data: {\"uuid\":\"1\",\"type\":\"textResponseChunk\",\"textResponse\":\"world\",\"sources\":[],\"close\":true,\"error\":false}",
+        );
+        let mock = server
+            .mock("POST", "/api/v1/workspace/test/stream-chat")
+            .with_header("content-type", "text/event-stream")
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let chunks: Vec<_> = client
+            .stream_workspace_slug_chat("test", "hi", &ChatMode::Chat)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        mock.assert_async().await;
+        let text: String = chunks
+            .iter()
+            .filter_map(|c| match c {
+                Ok(StreamChatChunk::Text(t)) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "Hello world");
+        assert!(matches!(chunks.last(), Some(Ok(StreamChatChunk::Close))));
+    }
+
+    #[tokio::test]
+    async fn test_stream_chat_holds_chat_limit() {
+        use ayda::anythingllm::ChatMode;
+        use futures::StreamExt;
+
+        let mut server = mockito::Server::new_async().await;
+        let body = "data: {\"uuid\":\"1\",\"type\":\"textResponseChunk\",\"textResponse\":\"Hi\",\"sources\":[],\"close\":true,\"error\":false}\n\n";
+        let mock = server
+            .mock("POST", "/api/v1/workspace/test/stream-chat")
+            .with_header("content-type", "text/event-stream")
+            .with_body(body)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let options = ClientOptions {
+            limits: Limits {
+                chats: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let client = AnythingLLMClient::with_options(&server.url(), "api_key", &options).unwrap();
+
+        // a second chat waits while the first response is still being read
+        let first = client
+            .stream_workspace_slug_chat("test", "hi", &ChatMode::Chat)
+            .await
+            .unwrap();
+        let second = tokio::time::timeout(
+            Duration::from_millis(200),
+            client.stream_workspace_slug_chat("test", "hi", &ChatMode::Chat),
+        )
+        .await;
+        assert!(second.is_err());
+
+        drop(first);
+        let second: Vec<_> = client
+            .stream_workspace_slug_chat("test", "hi", &ChatMode::Chat)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        mock.assert_async().await;
+        assert!(!second.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_post_document_raw_text() {
+        use ayda::anythingllm::RawTextMetadata;
+        use mockito::Matcher;
+        use serde_json::json;
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/v1/document/raw-text")
+            .match_body(Matcher::Json(json!({
+                "textContent": "Interglacials of the last 800,000 years are short and variable.",
+                "metadata": {
+                    "title": "Notes on Berger 2016",
+                    "docAuthor": "Richard Lyon",
+                    "docSource": "zotero"
+                }
+            })))
+            .with_body_from_file("tests/responses/AnythingLLM/post_document_raw_text.json")
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let metadata = RawTextMetadata {
+            title: "Notes on Berger 2016".to_string(),
+            author: Some("Richard Lyon".to_string()),
+            source: Some("zotero".to_string()),
+            ..Default::default()
+        };
+        let doc = client
+            .post_document_raw_text(
+                "Interglacials of the last 800,000 years are short and variable.",
+                &metadata,
+            )
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(doc.title.unwrap(), "Notes on Berger 2016");
+        assert!(doc.location.unwrap().starts_with("custom-documents/"));
+    }
+
+    #[tokio::test]
+    async fn test_post_document_upload_link() {
+        use mockito::Matcher;
+        use serde_json::json;
+
+        // the server fetches the page itself, so the link needn't resolve
+        let link = "https://example.com/interglacials";
+
+        let mut server = mockito::Server::new_async().await;
+        let ok = server
+            .mock("POST", "/api/v1/document/upload-link")
+            .match_body(Matcher::Json(json!({ "link": link })))
+            .with_body_from_file("tests/responses/AnythingLLM/post_document_raw_text.json")
+            .create_async()
+            .await;
+        let failed = server
+            .mock("POST", "/api/v1/document/upload-link")
+            .match_body(Matcher::Json(
+                json!({ "link": "http://unreachable.invalid" }),
+            ))
+            .with_body(r#"{"success":false,"error":"Could not scrape link","documents":[]}"#)
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let doc = client.post_document_upload_link(link).await.unwrap();
+        let error = client
+            .post_document_upload_link("http://unreachable.invalid")
+            .await;
+
+        ok.assert_async().await;
+        failed.assert_async().await;
+        assert!(doc.location.is_some());
+        assert!(
+            matches!(error, Err(LLMError::ServiceError(ref m)) if m == "Could not scrape link")
+        );
+    }
+
     #[tokio::test]
     async fn test_post_document_upload_invalid() {}
 
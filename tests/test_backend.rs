@@ -0,0 +1,132 @@
+// retrieval backend tests, run against an in-memory backend
+
+mod common;
+
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::common::{MemoryBackend, MemoryZotero};
+    use ayda::app::commands::workspace;
+    use ayda::app::commands::workspace::import::embed_and_verify;
+    use ayda::backend::RetrievalBackend;
+    use ayda::zotero::cache::LibraryCache;
+    use ayda::zotero::collection::models::Collection;
+    use ayda::zotero::item::models::Item;
+    use serde_json::json;
+
+    // commands run against any backend
+
+    #[tokio::test]
+    async fn test_create_command() {
+        let backend = MemoryBackend::default();
+
+        workspace::create(&backend, "climate".to_string())
+            .await
+            .unwrap();
+        workspace::create(&backend, "climate".to_string())
+            .await
+            .unwrap();
+
+        let workspaces = backend.workspaces().await.unwrap();
+        assert_eq!(workspaces.len(), 1);
+        assert_eq!(workspaces[0].name, "climate");
+    }
+
+    // documents that the backend doesn't embed are reported as missing
+
+    #[tokio::test]
+    async fn test_embed_and_verify() {
+        let backend = MemoryBackend {
+            rejected: vec!["custom-documents/b.json".to_string()],
+            ..Default::default()
+        };
+        let locations = vec![
+            "custom-documents/a.json".to_string(),
+            "custom-documents/b.json".to_string(),
+        ];
+
+        let missing = embed_and_verify(&backend, "climate", locations, 2)
+            .await
+            .unwrap();
+        let embedded = backend.workspace_documents("climate").await.unwrap();
+
+        assert_eq!(missing, vec!["custom-documents/b.json".to_string()]);
+        assert_eq!(embedded.len(), 1);
+    }
+
+    // the default implementation deletes every workspace
+
+    #[tokio::test]
+    async fn test_delete_all_workspaces() {
+        let backend = MemoryBackend::default();
+        backend.create_workspace("climate").await.unwrap();
+        backend.create_workspace("covid").await.unwrap();
+
+        backend.delete_all_workspaces().await.unwrap();
+
+        assert!(backend.workspaces().await.unwrap().is_empty());
+    }
+
+    // web page items in a Zotero collection are uploaded by their URL and embedded, and a page
+    // that fails to upload doesn't stop the others
+
+    #[tokio::test]
+    async fn test_import_zotero_web_pages() {
+        let webpage = |key: &str, url: &str| {
+            serde_json::from_value::<Item>(json!({
+                "key": key,
+                "version": 1,
+                "itemType": "webpage",
+                "title": key,
+                "url": url,
+                "dateAdded": "2024-03-13T10:00:00Z",
+                "dateModified": "2024-03-13T10:00:00Z",
+                "collections": ["WEB00001"],
+                "creators": [],
+                "tags": [],
+            }))
+            .unwrap()
+        };
+        let collection = Collection {
+            key: "WEB00001".to_string(),
+            name: "Web".to_string(),
+            parent_collection: None,
+            num_collections: 0,
+            num_items: 2,
+        };
+        let zotero = MemoryZotero {
+            cache: LibraryCache {
+                version: 1,
+                collections: BTreeMap::from([(collection.key.clone(), collection)]),
+                items: BTreeMap::from([
+                    (
+                        "PAGE0001".to_string(),
+                        webpage("PAGE0001", "https://example.com/a"),
+                    ),
+                    (
+                        "PAGE0002".to_string(),
+                        webpage("PAGE0002", "https://example.com/b"),
+                    ),
+                ]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let backend = MemoryBackend {
+            broken_links: vec!["https://example.com/b".to_string()],
+            ..Default::default()
+        };
+
+        workspace::import_zotero(&backend, &zotero, "Web".to_string(), 0, false)
+            .await
+            .unwrap();
+
+        let workspace = backend.workspace_by_name("zotero-Web").await.unwrap();
+        let embedded = backend.workspace_documents(&workspace.slug).await.unwrap();
+        assert_eq!(embedded.len(), 1);
+        assert_eq!(
+            embedded[0].docpath,
+            "custom-documents/https://example.com/a.json"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+// offline backend integration tests
+
+mod tests {
+    use std::path::PathBuf;
+
+    use ayda::anythingllm::ChatMode;
+    use ayda::backend::local::LocalBackend;
+    use ayda::backend::RetrievalBackend;
+
+    // documents can be uploaded, embedded and queried, and the index persists
+
+    #[tokio::test]
+    async fn test_query_returns_ranked_passages() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("berger.txt");
+        std::fs::write(
+            &notes,
+            "Interglacials of the last 800,000 years vary in length and intensity. \
+             Orbital forcing sets the timing of each interglacial.",
+        )
+        .unwrap();
+        let other = dir.path().join("covid.md");
+        std::fs::write(&other, "Transmission of respiratory viruses in schools.").unwrap();
+
+        let backend = LocalBackend::new(dir.path().join("data"));
+        let workspace = backend.create_workspace("climate").await.unwrap();
+        let mut locations = Vec::new();
+        for path in [&notes, &other] {
+            let doc = backend.upload_document(path).await.unwrap();
+            locations.push(doc.location.unwrap());
+        }
+        backend
+            .embed_documents(&workspace.slug, locations)
+            .await
+            .unwrap();
+
+        // reopen the backend to read the persisted index
+        let backend = LocalBackend::new(dir.path().join("data"));
+        let response = backend
+            .chat(
+                &workspace.slug,
+                None,
+                "interglacial timing",
+                &ChatMode::Query,
+            )
+            .await
+            .unwrap();
+        let documents = backend.workspace_documents(&workspace.slug).await.unwrap();
+
+        assert_eq!(documents.len(), 2);
+        assert_eq!(response.sources.len(), 1);
+        assert_eq!(response.sources[0].title, "berger.txt");
+        assert!(response.sources[0].score > 0.0);
+        assert!(response.text_response.contains("Orbital forcing"));
+    }
+
+    // text is extracted from PDFs
+
+    #[tokio::test]
+    async fn test_upload_pdf() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = LocalBackend::new(dir.path().to_path_buf());
+        let workspace = backend.create_workspace("pdfs").await.unwrap();
+
+        let doc = backend
+            .upload_document(&PathBuf::from(
+                "tests/test_data/DELETE ME test document.pdf",
+            ))
+            .await
+            .unwrap();
+        backend
+            .embed_documents(&workspace.slug, vec![doc.location.unwrap()])
+            .await
+            .unwrap();
+
+        let documents = backend.workspace_documents(&workspace.slug).await.unwrap();
+        assert_eq!(documents.len(), 1);
+        assert!(documents[0].metadata.word_count > 0);
+    }
+
+    // deleting a workspace removes it and its index
+
+    #[tokio::test]
+    async fn test_delete_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = LocalBackend::new(dir.path().to_path_buf());
+        let first = backend.create_workspace("climate").await.unwrap();
+        let second = backend.create_workspace("climate").await.unwrap();
+        assert_ne!(first.slug, second.slug);
+
+        backend.delete_workspace(&first.slug).await.unwrap();
+
+        let workspaces = backend.workspaces().await.unwrap();
+        assert_eq!(workspaces.len(), 1);
+        assert_eq!(workspaces[0].slug, second.slug);
+    }
+
+    // documents embedded in a workspace at the same time are all kept
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_embeds() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = std::sync::Arc::new(LocalBackend::new(dir.path().to_path_buf()));
+        let workspace = backend.create_workspace("climate").await.unwrap();
+
+        let mut tasks = Vec::new();
+        for n in 0..8 {
+            let path = dir.path().join(format!("notes_{}.txt", n));
+            std::fs::write(&path, format!("Notes on interglacial number {}.", n)).unwrap();
+            let location = backend
+                .upload_document(&path)
+                .await
+                .unwrap()
+                .location
+                .unwrap();
+
+            let backend = backend.clone();
+            let slug = workspace.slug.clone();
+            tasks.push(tokio::spawn(async move {
+                backend.embed_documents(&slug, vec![location]).await
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        let documents = backend.workspace_documents(&workspace.slug).await.unwrap();
+        assert_eq!(documents.len(), 8);
+    }
+
+    // deleting an uploaded document removes it from the store and from workspaces
+
+    #[tokio::test]
+    async fn test_delete_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("berger.txt");
+        std::fs::write(&notes, "Interglacials of the last 800,000 years.").unwrap();
+
+        let backend = LocalBackend::new(dir.path().join("data"));
+        let workspace = backend.create_workspace("climate").await.unwrap();
+        let location = backend
+            .upload_document(&notes)
+            .await
+            .unwrap()
+            .location
+            .unwrap();
+        backend
+            .embed_documents(&workspace.slug, vec![location.clone()])
+            .await
+            .unwrap();
+
+        backend
+            .delete_documents(vec![location.clone()])
+            .await
+            .unwrap();
+
+        let documents = backend.workspace_documents(&workspace.slug).await.unwrap();
+        assert!(documents.is_empty());
+        // the upload is gone, so it can't be embedded again
+        backend
+            .embed_documents(&workspace.slug, vec![location])
+            .await
+            .unwrap();
+        assert!(backend
+            .workspace_documents(&workspace.slug)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
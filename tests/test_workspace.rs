@@ -3,10 +3,14 @@
 mod common;
 
 mod tests {
-    use crate::common::AnythingLLMFixture;
+    use crate::common::{mock_anythingllm_client, AnythingLLMFixture};
     use ayda::anythingllm::client::AnythingLLMClient;
+    use ayda::anythingllm::error::LLMError;
+    use ayda::anythingllm::{ChatRole, WorkspaceSettings};
     use ayda::app::commands::workspace::import::UpdateParameter;
     use ayda::Config;
+    use mockito::Matcher;
+    use serde_json::json;
     use std::path::PathBuf;
 
     // getting a list of workspaces returns a non-zero list of Workspace objects
@@ -20,10 +24,8 @@ mod tests {
                 let workspaces = c.get_workspaces().await.unwrap();
                 let workspace_slug = &w.slug;
 
-                assert!(workspaces.len() > 0);
-                assert!(workspaces
-                    .iter()
-                    .any(|w| w.slug == workspace_slug.to_string()));
+                assert!(!workspaces.is_empty());
+                assert!(workspaces.iter().any(|w| &w.slug == workspace_slug));
             })
             .await;
     }
@@ -38,13 +40,14 @@ mod tests {
             &config.anythingllm_ip,
             &config.anythingllm_port,
             &config.anythingllm_api_key,
-        );
-        let before_count = client.get_workspaces().await.unwrap().len();
+        )
+        .unwrap();
+        let _before_count = client.get_workspaces().await.unwrap().len();
         let fixture = AnythingLLMFixture::new();
 
         fixture
-            .with_fixture(|w, c| async move {
-                let after_count = c.get_workspaces().await.unwrap().len();
+            .with_fixture(|_w, c| async move {
+                let _after_count = c.get_workspaces().await.unwrap().len();
                 // assert_eq!(after_count, before_count + 1);
             })
             .await;
@@ -59,7 +62,7 @@ mod tests {
         fixture
             .with_fixture(|w, c| async move {
                 let test_workspace_slug = &w.slug;
-                let workspace = c.get_workspace_by_slug(&test_workspace_slug).await.unwrap();
+                let workspace = c.get_workspace_by_slug(test_workspace_slug).await.unwrap();
 
                 assert_eq!(workspace.slug, test_workspace_slug.to_string());
             })
@@ -73,7 +76,7 @@ mod tests {
     async fn test_get_workspace_by_invalid_slug() {
         let fixture = AnythingLLMFixture::new();
         fixture
-            .with_fixture(|w, c| async move {
+            .with_fixture(|_w, c| async move {
                 let workspace = c.get_workspace_by_slug("invalid-workspace-slug").await;
                 assert!(workspace.is_err());
             })
@@ -102,7 +105,7 @@ mod tests {
     async fn test_workspace_from_name_is_invalid() {
         let fixture = AnythingLLMFixture::new();
         fixture
-            .with_fixture(|w, c| async move {
+            .with_fixture(|_w, c| async move {
                 let workspace = c.get_workspace_by_name("Invalid workspace name").await;
                 assert!(workspace.is_err());
             })
@@ -135,8 +138,7 @@ mod tests {
                 // NOTE: this transfers the document from the file system to the workspace only
                 let test_workspace_slug = &w.slug;
                 let docs = vec![doc.clone().location.unwrap()];
-                let _ = c
-                    .update_embeddings(&test_workspace_slug, docs, UpdateParameter::Adds)
+                c.update_embeddings(test_workspace_slug, docs, UpdateParameter::Adds)
                     .await
                     .unwrap();
 
@@ -145,7 +147,7 @@ mod tests {
 
                 // verify the document is in the workspace
                 let documents = c
-                    .get_workspace_by_slug(&test_workspace_slug)
+                    .get_workspace_by_slug(test_workspace_slug)
                     .await
                     .unwrap()
                     .documents
@@ -158,11 +160,270 @@ mod tests {
                 // NOTE: this test requires and assumes document deletion works
 
                 let doc_vec = vec![doc.clone().location.unwrap()];
-                let _ = c.delete_api_system_remove_documents(doc_vec).await.unwrap();
+                c.delete_api_system_remove_documents(doc_vec).await.unwrap();
             })
             .await;
     }
 
+    // updating workspace settings should only send the settings that change
+
+    #[tokio::test]
+    async fn test_update_workspace_settings() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/v1/workspace/test-workspace/update")
+            .match_body(Matcher::Json(json!({ "openAiTemp": 0.2, "topN": 6 })))
+            .with_body_from_file("tests/responses/AnythingLLM/post_workspace_new.json")
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let settings = WorkspaceSettings {
+            temperature: Some(0.2),
+            top_n: Some(6),
+            ..Default::default()
+        };
+        let workspace = client
+            .update_workspace_settings("test-workspace", &settings)
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(workspace.top_n, Some(4));
+    }
+
+    // getting the chat history should return the messages with their sources
+
+    #[tokio::test]
+    async fn test_get_workspace_chats() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/workspace/test-workspace/chats")
+            .with_body_from_file("tests/responses/AnythingLLM/get_workspace_chats.json")
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let chats = client.get_workspace_chats("test-workspace").await.unwrap();
+
+        assert_eq!(chats.len(), 4);
+        assert_eq!(chats[0].role, ChatRole::User);
+        assert_eq!(chats[1].role, ChatRole::Assistant);
+        assert_eq!(chats[1].sources.len(), 1);
+    }
+
+    // getting the documents in a workspace should return their metadata
+
+    #[tokio::test]
+    async fn test_get_workspace_documents() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/workspace/test-workspace")
+            .with_body_from_file("tests/responses/AnythingLLM/get_workspace.json")
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let documents = client
+            .get_workspace_documents("test-workspace")
+            .await
+            .unwrap();
+
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].metadata.title, "DELETE ME test document.pdf");
+        assert_eq!(documents[0].metadata.doc_author, "Springer");
+        assert_eq!(documents[0].metadata.word_count, 8259);
+        assert!(!documents[0].pinned);
+    }
+
+    // documents that don't appear in the workspace after embedding are retried and reported
+
+    #[tokio::test]
+    async fn test_embed_and_verify_reports_missing() {
+        use ayda::app::commands::workspace::import::embed_and_verify;
+
+        let embedded = "custom-documents/DELETE-ME-test-document.pdf-657b4057-bfb5-4ee3-8399-1f74d59b86f1.json";
+        let missing = "custom-documents/missing.pdf-00000000-0000-0000-0000-000000000000.json";
+
+        let mut server = mockito::Server::new_async().await;
+        let adds = server
+            .mock("POST", "/api/v1/workspace/test-workspace/update-embeddings")
+            .match_body(Matcher::Json(json!({ "adds": [embedded, missing] })))
+            .expect(1)
+            .create_async()
+            .await;
+        let retry = server
+            .mock("POST", "/api/v1/workspace/test-workspace/update-embeddings")
+            .match_body(Matcher::Json(json!({ "adds": [missing] })))
+            .expect(1)
+            .create_async()
+            .await;
+        let get = server
+            .mock("GET", "/api/v1/workspace/test-workspace")
+            .with_body_from_file("tests/responses/AnythingLLM/get_workspace.json")
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let locations = vec![embedded.to_string(), missing.to_string()];
+        let result = embed_and_verify(&client, "test-workspace", locations, 1)
+            .await
+            .unwrap();
+
+        adds.assert_async().await;
+        retry.assert_async().await;
+        get.assert_async().await;
+        assert_eq!(result, vec![missing.to_string()]);
+    }
+
+    // threads can be created, found by name, renamed and deleted
+
+    #[tokio::test]
+    async fn test_create_thread() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/v1/workspace/test-workspace/thread/new")
+            .match_body(Matcher::Json(json!({ "name": "paleoclimate" })))
+            .with_body_from_file("tests/responses/AnythingLLM/post_thread_new.json")
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let thread = client
+            .create_thread("test-workspace", "paleoclimate")
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(thread.name, "paleoclimate");
+        assert_eq!(thread.workspace_id, Some(601));
+    }
+
+    #[tokio::test]
+    async fn test_get_thread_by_name() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/workspace/test-workspace")
+            .with_body_from_file("tests/responses/AnythingLLM/get_workspace.json")
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let thread = client
+            .get_thread_by_name("test-workspace", "paleoclimate")
+            .await
+            .unwrap();
+        let missing = client
+            .get_thread_by_name("test-workspace", "glaciology")
+            .await;
+
+        assert_eq!(thread.slug, "5c4fcd5a-4fb0-4cbd-8a94-2b8c1f9d1a6e");
+        assert!(matches!(missing, Err(LLMError::ThreadNotFoundError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rename_and_delete_thread() {
+        let mut server = mockito::Server::new_async().await;
+        let rename = server
+            .mock("POST", "/api/v1/workspace/test-workspace/thread/t1/update")
+            .match_body(Matcher::Json(json!({ "name": "paleoclimate" })))
+            .with_body_from_file("tests/responses/AnythingLLM/post_thread_new.json")
+            .create_async()
+            .await;
+        let delete = server
+            .mock("DELETE", "/api/v1/workspace/test-workspace/thread/t1")
+            .with_body("OK")
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let thread = client
+            .rename_thread("test-workspace", "t1", "paleoclimate")
+            .await
+            .unwrap();
+        client.delete_thread("test-workspace", "t1").await.unwrap();
+
+        rename.assert_async().await;
+        delete.assert_async().await;
+        assert_eq!(thread.name, "paleoclimate");
+    }
+
+    #[tokio::test]
+    async fn test_thread_chat() {
+        use ayda::anythingllm::ChatMode;
+
+        let mut server = mockito::Server::new_async().await;
+        let chat = server
+            .mock("POST", "/api/v1/workspace/test-workspace/thread/t1/chat")
+            .match_body(Matcher::Json(
+                json!({ "message": "What is an interglacial?", "mode": "chat" }),
+            ))
+            .with_body_from_file("tests/responses/AnythingLLM/post_workspace_slug_chat.json")
+            .create_async()
+            .await;
+        let chats = server
+            .mock("GET", "/api/v1/workspace/test-workspace/thread/t1/chats")
+            .with_body_from_file("tests/responses/AnythingLLM/get_workspace_chats.json")
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        client
+            .post_thread_chat(
+                "test-workspace",
+                "t1",
+                "What is an interglacial?",
+                &ChatMode::Chat,
+            )
+            .await
+            .unwrap();
+        let history = client
+            .get_thread_chats("test-workspace", "t1")
+            .await
+            .unwrap();
+
+        chat.assert_async().await;
+        chats.assert_async().await;
+        assert!(!history.is_empty());
+    }
+
+    // a document can be found by part of its title and pinned
+
+    #[tokio::test]
+    async fn test_pin_document_by_title() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/workspace/test-workspace")
+            .with_body_from_file("tests/responses/AnythingLLM/get_workspace.json")
+            .create_async()
+            .await;
+        let pin = server
+            .mock("POST", "/api/v1/workspace/test-workspace/update-pin")
+            .match_body(Matcher::Json(json!({
+                "docPath": "custom-documents/DELETE-ME-test-document.pdf-657b4057-bfb5-4ee3-8399-1f74d59b86f1.json",
+                "pinStatus": true
+            })))
+            .create_async()
+            .await;
+
+        let client = mock_anythingllm_client(&server);
+        let document = client
+            .get_workspace_document_by_name("test-workspace", "test document")
+            .await
+            .unwrap();
+        client
+            .update_pin("test-workspace", &document.docpath, true)
+            .await
+            .unwrap();
+
+        pin.assert_async().await;
+        assert!(client
+            .get_workspace_document_by_name("test-workspace", "no such paper")
+            .await
+            .is_err());
+    }
+
     // deleting a workspace should remove the workspace from the list of workspaces
     // and all embedded documents in the workspace
 
@@ -174,20 +435,19 @@ mod tests {
                 // add a document to the file system
                 let test_doc_filepath =
                     PathBuf::from("tests/test_data/DELETE ME test document.pdf");
-                let doc_before_count = c.get_documents().await.unwrap().len();
+                let _doc_before_count = c.get_documents().await.unwrap().len();
                 let doc = c.post_document_upload(&test_doc_filepath).await.unwrap();
 
                 // embed the document in the workspace
                 let test_workspace_slug = &w.slug;
                 let docs = vec![doc.clone().location.unwrap()];
-                let _ = c
-                    .update_embeddings(&test_workspace_slug, docs, UpdateParameter::Adds)
+                c.update_embeddings(test_workspace_slug, docs, UpdateParameter::Adds)
                     .await
                     .unwrap();
 
                 // delete the workspace
                 let workspace_slug = &w.slug;
-                let _ = c.delete_workspace_slug(&workspace_slug).await.unwrap();
+                c.delete_workspace_slug(workspace_slug).await.unwrap();
             })
             .await;
     }
@@ -199,7 +459,7 @@ mod tests {
         // get a workspace fixture
         let fixture = AnythingLLMFixture::new();
         fixture
-            .with_fixture(|w, c| async move {
+            .with_fixture(|_w, c| async move {
                 let result = c.delete_workspace_slug("invalid slug").await;
 
                 assert!(result.is_err());
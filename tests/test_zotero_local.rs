@@ -0,0 +1,115 @@
+mod common;
+
+mod tests {
+    use ayda::zotero::library::Library;
+    use ayda::zotero::local::LocalZotero;
+    use ayda::zotero::source::ZoteroSource;
+
+    use crate::common::zotero_database;
+
+    // Collections ///////////////////////////////////////////////////////////////////////////////
+
+    #[tokio::test]
+    async fn test_read_collections() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = LocalZotero::new(zotero_database(dir.path()), Library::User);
+        let library = local.read_library().await.unwrap();
+
+        assert_eq!(library.version, 1234);
+        assert_eq!(library.collections.len(), 2);
+
+        let paleo = library.collection_from_name("climate/paleo").unwrap();
+        assert_eq!(paleo.key, "PALEO001");
+        assert_eq!(paleo.parent_collection.as_deref(), Some("CLIMATE1"));
+
+        // the trashed item isn't counted
+        let climate = &library.collections["CLIMATE1"];
+        assert_eq!(climate.num_collections, 1);
+        assert_eq!(climate.num_items, 3);
+    }
+
+    // Items /////////////////////////////////////////////////////////////////////////////////////
+
+    #[tokio::test]
+    async fn test_read_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = LocalZotero::new(zotero_database(dir.path()), Library::User);
+        let library = local.read_library().await.unwrap();
+
+        // notes and trashed items are skipped
+        let mut keys: Vec<&str> = library.items.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec!["LINKED01", "PAPER001", "PAPER002", "PDF00001", "PDF00002", "WEBPAGE1"]
+        );
+
+        let paper = &library.items["PAPER001"];
+        assert_eq!(paper.title, "Ice core records of past climate");
+        assert_eq!(
+            paper.abstract_note.as_deref(),
+            Some("A review of ice core records.")
+        );
+        assert_eq!(paper.collections, vec!["CLIMATE1"]);
+        assert_eq!(paper.date_added.to_rfc3339(), "2024-03-10T21:48:42+00:00");
+        let creators = paper.creators.as_ref().unwrap();
+        assert_eq!(creators[0].last_name, "Smith");
+        assert_eq!(creators[1].last_name, "Doe");
+        assert_eq!(paper.tags.as_ref().unwrap().len(), 2);
+
+        let pdf = &library.items["PDF00001"];
+        assert!(pdf.is_pdf());
+        assert_eq!(pdf.parent_item.as_deref(), Some("PAPER001"));
+        assert_eq!(
+            pdf.filepath(std::path::Path::new("/storage")),
+            Some(std::path::PathBuf::from("/storage/PDF00001/Ice cores.pdf"))
+        );
+
+        // a linked file isn't in the storage directory
+        assert_eq!(
+            library.items["LINKED01"].filepath(std::path::Path::new("/storage")),
+            None
+        );
+
+        assert!(library.items["WEBPAGE1"].is_webpage());
+    }
+
+    #[tokio::test]
+    async fn test_read_collection_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = LocalZotero::new(zotero_database(dir.path()), Library::User);
+        let library = local.read_library().await.unwrap();
+
+        assert_eq!(library.collection_items("CLIMATE1").len(), 4);
+        assert_eq!(library.collection_items_recursive("CLIMATE1").len(), 6);
+    }
+
+    // Libraries /////////////////////////////////////////////////////////////////////////////////
+
+    #[tokio::test]
+    async fn test_read_group_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = LocalZotero::new(zotero_database(dir.path()), Library::Group(4711));
+        let library = local.read_library().await.unwrap();
+
+        assert_eq!(library.version, 56);
+        assert_eq!(library.collection_items("READING1").len(), 2);
+        assert!(!library.items.contains_key("PAPER001"));
+    }
+
+    #[tokio::test]
+    async fn test_read_missing_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = LocalZotero::new(zotero_database(dir.path()), Library::Group(1));
+
+        assert!(local.read_library().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_missing_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = LocalZotero::new(dir.path().join("zotero.sqlite"), Library::User);
+
+        assert!(local.read_library().await.is_err());
+    }
+}